@@ -0,0 +1,351 @@
+use std::{collections::BTreeMap, fmt::Write, fs, path::Path, process::exit};
+
+use crate::{
+    args::Args,
+    definitions::type_declarations,
+    lexer::{Token, tokenize},
+    project::SourceMap,
+};
+
+/// Moonwave tags that are part of the contract of a member. Descriptions
+/// after `--` are dropped, so rewording the docs is not an API change.
+const ANNOTATIONS: [&str; 3] = ["@param", "@return", "@prop"];
+
+/// Keywords that start the body of a function defined on one line; return
+/// types never contain them.
+const BODY_KEYWORDS: [&str; 9] = [
+    "do", "end", "for", "if", "local", "repeat", "return", "while", ";",
+];
+
+/// Modules whose API is checked, by full name. Config and DeniedMembers are
+/// left out since they are generated when a policy file is used.
+const PUBLIC_MODULES: [&str; 3] = [
+    "Sandboxer",
+    "Sandboxer.InstanceList",
+    "Sandboxer.InstanceSandboxer",
+];
+
+/// An exported function, property or type of a module.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Member {
+    pub signature: String,
+    pub annotations: Vec<String>,
+}
+
+/// Members of each module by full name, sorted so the snapshot is stable.
+pub type Api = BTreeMap<String, BTreeMap<String, Member>>;
+
+fn at_line_start(source: &str, token: &Token) -> bool {
+    token.offset == 0 || source.as_bytes()[token.offset - 1] == b'\n'
+}
+
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// The annotations of the `--[=[ ]=]` doc comment ending right above `line`.
+fn doc_annotations(lines: &[&str], line: u32) -> Vec<String> {
+    let above = line as usize - 1;
+    if above == 0 || lines[above - 1].trim() != "]=]" {
+        return Vec::new();
+    }
+    let Some(start) = lines[..above - 1]
+        .iter()
+        .rposition(|line| line.trim_start().starts_with("--[=["))
+    else {
+        return Vec::new();
+    };
+
+    lines[start + 1..above - 1]
+        .iter()
+        .map(|line| line.trim())
+        .filter(|line| {
+            ANNOTATIONS
+                .iter()
+                .any(|tag| line.split_whitespace().next() == Some(tag))
+        })
+        .map(|line| collapse_whitespace(line.split_once("--").map_or(line, |(tag, _)| tag)))
+        .collect()
+}
+
+/// The signature of the function whose name starts at `tokens[0]`: its
+/// name, parameters and return type, up to the end of the line with the
+/// closing parenthesis.
+fn function_signature(source: &str, tokens: &[Token]) -> Option<String> {
+    let open = tokens.iter().position(|token| token.text == "(")?;
+    let mut depth = 0usize;
+    let close = open
+        + tokens[open..].iter().position(|token| {
+            match token.text {
+                "(" => depth += 1,
+                ")" => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?;
+    let line = tokens[close].line;
+    let last = tokens[close..]
+        .iter()
+        .take_while(|token| token.line == line && !BODY_KEYWORDS.contains(&token.text))
+        .last()?;
+    Some(collapse_whitespace(
+        &source[tokens[0].offset..last.offset + last.text.len()],
+    ))
+}
+
+/// Finds the members a module exports: top-level `function Module.name`,
+/// `function Module:name` and `Module.name = ...` statements, and exported
+/// types with their definitions. Functions win over forward declarations
+/// of the same member.
+pub fn members(source: &str, module: &str) -> BTreeMap<String, Member> {
+    let lines: Vec<_> = source.lines().collect();
+    let tokens = tokenize(source);
+    let declarations: BTreeMap<_, _> = type_declarations(source).into_iter().collect();
+    let mut members = BTreeMap::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        if !at_line_start(source, token) {
+            continue;
+        }
+        let next: Vec<_> = tokens[index + 1..]
+            .iter()
+            .take(3)
+            .map(|token| token.text)
+            .collect();
+        let annotations = || doc_annotations(&lines, token.line);
+
+        match (token.text, next.as_slice()) {
+            ("function", [name, "." | ":", member, ..]) if *name == module => {
+                let Some(signature) = function_signature(source, &tokens[index + 1..]) else {
+                    continue;
+                };
+                members.insert(
+                    (*member).to_owned(),
+                    Member {
+                        signature,
+                        annotations: annotations(),
+                    },
+                );
+            }
+            (name, [".", member, "="]) if name == module => {
+                let annotations = annotations();
+                let entry = members.entry((*member).to_owned()).or_insert(Member {
+                    signature: format!("{module}.{member}"),
+                    annotations: Vec::new(),
+                });
+                if entry.annotations.is_empty() {
+                    entry.annotations = annotations;
+                }
+            }
+            ("export", ["type", name, ..]) => {
+                let signature = declarations
+                    .get(*name)
+                    .map_or_else(|| format!("type {name}"), |d| collapse_whitespace(d));
+                members.insert(
+                    format!("type {name}"),
+                    Member {
+                        signature,
+                        annotations: annotations(),
+                    },
+                );
+            }
+            _ => {}
+        }
+    }
+    members
+}
+
+/// Extracts the API of the public modules in `sources`. A script's module
+/// table is named after the script, e.g. `InstanceList` for
+/// `Sandboxer.InstanceList`.
+pub fn extract(sources: &SourceMap) -> Api {
+    PUBLIC_MODULES
+        .iter()
+        .map(|&full_name| {
+            let Some(path) = sources.get(full_name) else {
+                panic!("Public module {full_name} is not part of the build");
+            };
+            let source = fs::read_to_string(path)
+                .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+            let module = full_name.rsplit('.').next().unwrap_or(full_name);
+            (full_name.to_owned(), members(&source, module))
+        })
+        .collect()
+}
+
+pub fn to_snapshot(api: &Api) -> String {
+    let mut out = String::from(
+        "-- Public Luau API of Sandboxer, checked by sandboxer-builder.\n\
+         -- After reviewing a change, update it with `cargo run -- --update-api`.\n",
+    );
+    for (module, members) in api {
+        writeln!(out, "\n{module}").unwrap();
+        for member in members.values() {
+            writeln!(out, "\t{}", member.signature).unwrap();
+            for annotation in &member.annotations {
+                writeln!(out, "\t\t{annotation}").unwrap();
+            }
+        }
+    }
+    out
+}
+
+/// Lines only in `old` (`-`) and lines only in `new` (`+`), with the module
+/// and member each line belongs to.
+pub fn differences(old: &str, new: &str) -> Vec<String> {
+    fn lines(snapshot: &str) -> Vec<String> {
+        let (mut module, mut member) = ("", "");
+        snapshot
+            .lines()
+            .filter(|line| !line.is_empty() && !line.starts_with("--"))
+            .filter_map(|line| {
+                if let Some(annotation) = line.strip_prefix("\t\t") {
+                    Some(format!("{module}: {member}: {annotation}"))
+                } else if let Some(signature) = line.strip_prefix('\t') {
+                    member = signature;
+                    Some(format!("{module}: {signature}"))
+                } else {
+                    module = line;
+                    None
+                }
+            })
+            .collect()
+    }
+    let (old, new) = (lines(old), lines(new));
+    let removed = old
+        .iter()
+        .filter(|line| !new.contains(line))
+        .map(|line| format!("- {line}"));
+    let added = new
+        .iter()
+        .filter(|line| !old.contains(line))
+        .map(|line| format!("+ {line}"));
+    removed.chain(added).collect()
+}
+
+/// Compares the API of the built scripts with the snapshot at
+/// `args.api_snapshot`, or rewrites the snapshot with `--update-api`.
+pub fn check(sources: &SourceMap, args: &Args) {
+    let path: &Path = &args.api_snapshot;
+    let snapshot = to_snapshot(&extract(sources));
+    if args.update_api {
+        fs::write(path, snapshot)
+            .unwrap_or_else(|e| panic!("Failed to write {}: {e}", path.display()));
+        info!("Wrote {}", path.display());
+        return;
+    }
+
+    let Ok(old) = fs::read_to_string(path) else {
+        panic!(
+            "Missing API snapshot {}; create it with --update-api",
+            path.display()
+        );
+    };
+    let differences = differences(&old, &snapshot);
+    if differences.is_empty() {
+        info!("Public API matches {}", path.display());
+        return;
+    }
+    for difference in &differences {
+        error!(file:% = path.display(); "{}: {difference}", path.display());
+    }
+    error!(
+        "The public API changed; review the changes above and update the snapshot with --update-api"
+    );
+    exit(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::project::Project;
+
+    const SOURCE: &str = "\
+local M = {}
+M.helper = nil :: any
+
+--[=[
+\t@within M
+\t@prop Version string
+\t@readonly
+
+\tThe version.
+]=]
+M.Version = \"1.0\"
+
+--[=[
+\t@param a number -- The first number.
+\t@param b -- The second number.
+\t@return number -- The sum.
+]=]
+function M.add(a: number,
+\tb: number): number
+\tlocal function inner() end
+\treturn a + b
+end
+
+function M:method()\t
+end
+
+function M.helper(): () end
+local N = {}
+function N.other() end
+export type Options = {
+	a: number,
+}
+return M";
+
+    #[test]
+    fn extracts_exported_members() {
+        let members = members(SOURCE, "M");
+        assert_eq!(
+            members.keys().collect::<Vec<_>>(),
+            ["Version", "add", "helper", "method", "type Options"]
+        );
+        assert_eq!(
+            members["add"],
+            Member {
+                signature: "M.add(a: number, b: number): number".to_owned(),
+                annotations: vec![
+                    "@param a number".to_owned(),
+                    "@param b".to_owned(),
+                    "@return number".to_owned()
+                ],
+            }
+        );
+        assert_eq!(members["Version"].annotations, ["@prop Version string"]);
+        assert_eq!(members["method"].signature, "M:method()");
+        assert_eq!(members["helper"].signature, "M.helper(): ()");
+        assert_eq!(
+            members["type Options"].signature,
+            "type Options = { a: number, }"
+        );
+    }
+
+    #[test]
+    fn reports_changed_lines() {
+        let old = "-- header\n\nM\n\tM.add(a: number)\n\t\t@param a number\n\tM.Version\n";
+        let new = "-- header\n\nM\n\tM.add(a: number)\n\t\t@param a string\n";
+        assert_eq!(
+            differences(old, new),
+            [
+                "- M: M.add(a: number): @param a number",
+                "- M: M.Version",
+                "+ M: M.add(a: number): @param a string"
+            ]
+        );
+        assert!(differences(old, old).is_empty());
+    }
+
+    #[test]
+    fn snapshot_is_up_to_date() {
+        // tests run from the builder directory
+        let (_, sources) = Project::read("../default.project.json").build(Path::new(".."));
+        let snapshot = fs::read_to_string("../api.snapshot").unwrap();
+        assert_eq!(
+            differences(&snapshot, &to_snapshot(&extract(&sources))),
+            Vec::<String>::new(),
+            "run `cargo run -- --update-api` from the repository root"
+        );
+    }
+}
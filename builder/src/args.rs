@@ -0,0 +1,452 @@
+use std::{
+    env::args,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use crate::{
+    license::LicenseMode,
+    logs::{LogFormat, LogLevel},
+};
+
+const USAGE: &str = "\
+Usage: sandboxer-builder [COMMAND] [OPTIONS]
+
+Commands:
+  test                 Build Sandboxer and run the tests on Roblox (default)
+  instance-list        Classify engine services from the bundled API dump and print the InstanceList tables
+  audit                Report unhooked members of allowed classes that may yield or have side effects
+  mutate               Run the tests against mutated sources and report the mutants that survive
+  watch                Rerun the affected tests whenever a Luau source or test changes
+  bench                Measure the sandbox overhead and write bench.json
+  publish              Upload the tested Sandboxer.rbxm as a new asset version and write release.json
+
+Options:
+  --project <PATH>     Rojo project file describing the Sandboxer tree [default: default.project.json]
+  --policy <PATH>      Sandbox policy compiled into the Config module [default: sandboxer.toml, if present]
+  --xml                Also write Sandboxer.rbxmx (XML) for reviewing diffs
+  --reproducible       Build byte-identical artifacts and write SHA256SUMS
+  --build-version <V>  Version to stamp into the build [default: from git tag or README]
+  --license <MODE>     How to embed the license: module, spdx, string-value, omit [default: module]
+  --minify             Strip doc comments and whitespace, writing Sandboxer.linemap.json
+  --coverage           Instrument the Luau sources and write line coverage to lcov.info
+  --filter <PATTERN>   Only run tests whose full name contains PATTERN
+  --suite <NAME>       Only load the test module NAME (e.g. `Integration`)
+  --exclude <PATTERN>  Skip tests whose full name contains PATTERN
+  --shards <N>         Split test modules across N execution tasks [default: 1]
+  --timeout <SECONDS>  Timeout of each execution task, e.g. `30s` [default: 10s]
+  --deadline <SECONDS> Cancel all outstanding tasks after SECONDS in total (publish waits up to 600s by default)
+  --binary-cache <PATH> Reuse uploads of identical test binaries for 24 hours [default: .sandboxer-cache/binary-inputs.json]
+  --no-binary-cache    Upload every test binary
+  --log-ignore <REGEX> Drop Luau log messages matching REGEX
+  --log-level <LEVEL>  Minimum Luau log level: output, info, warning, error [default: output]
+  --log-format <FMT>   Luau log format: pretty, plain, json [default: pretty]
+  --overrides <PATH>   Reviewed service classification for instance-list [default: instance-list.toml]
+  --check              With instance-list, fail if the policy does not match the classification
+  --api-dump <PATH>    Full-API-Dump.json to check DeniedMembers and audit against, required by audit
+  --module <NAME>      With mutate, only mutate the script NAME (e.g. `InstanceList`)
+  --jobs <N>           With mutate, run up to N mutants at once [default: 4]
+  --dry-run            With mutate, list the mutants without running them
+  --baseline <PATH>    With bench, compare against earlier results and fail on regressions
+  --threshold <PERCENT> With bench, how much the overhead may grow over the baseline [default: 10]
+  --api-snapshot <PATH> Snapshot of the public Luau API checked by test [default: api.snapshot]
+  --update-api         Rewrite the API snapshot instead of failing when the API changed
+  --asset-id <ID>      With publish, the Roblox asset to upload a new version of
+  --tested <PATH>      Builds whose full test suite passed; publish refuses others [default: tested.json]
+  --api-url <URL>      Base URL of the Open Cloud API, e.g. of a mock server [default: https://apis.roblox.com]
+  --log-file <PATH>    Also write the builder's log to PATH, without colors
+  -v, --verbose        Log debug messages; twice for trace messages (overrides RUST_LOG)
+  -q, --quiet          Only log warnings and errors; twice for errors only (overrides RUST_LOG)
+  --no-color           Disable colored output (also respects NO_COLOR)
+  -h, --help           Print this message
+
+--filter, --suite, --exclude, --log-ignore and --module can be given more than once.
+With bench, --filter, --suite and --exclude select benchmarks instead of tests.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Command {
+    Test,
+    InstanceList,
+    Audit,
+    Mutate,
+    Watch,
+    Bench,
+    Publish,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct Args {
+    pub command: Command,
+    pub project: PathBuf,
+    pub policy: Option<PathBuf>,
+    pub xml: bool,
+    pub reproducible: bool,
+    pub build_version: Option<String>,
+    pub license: LicenseMode,
+    pub minify: bool,
+    pub coverage: bool,
+    pub filter: Vec<String>,
+    pub suite: Vec<String>,
+    pub exclude: Vec<String>,
+    pub shards: usize,
+    /// Task timeout in the `google.protobuf.Duration` JSON format (`"10s"`).
+    pub timeout: String,
+    pub deadline: Option<Duration>,
+    /// `None` with `--no-binary-cache`.
+    pub binary_cache: Option<PathBuf>,
+    pub log_ignore: Vec<String>,
+    pub log_level: LogLevel,
+    pub log_format: LogFormat,
+    pub overrides: PathBuf,
+    pub check: bool,
+    pub api_dump: Option<PathBuf>,
+    pub modules: Vec<String>,
+    pub jobs: usize,
+    pub dry_run: bool,
+    pub baseline: Option<PathBuf>,
+    /// Percent the overhead of a benchmark may grow over the baseline.
+    pub threshold: u32,
+    pub api_snapshot: PathBuf,
+    pub update_api: bool,
+    pub asset_id: Option<u64>,
+    pub tested: PathBuf,
+    pub api_url: String,
+    /// `-v` counts up and `-q` counts down.
+    pub verbosity: i8,
+    pub log_file: Option<PathBuf>,
+    pub no_color: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            command: Command::Test,
+            project: PathBuf::from("default.project.json"),
+            policy: None,
+            xml: false,
+            reproducible: false,
+            build_version: None,
+            license: LicenseMode::Module,
+            minify: false,
+            coverage: false,
+            filter: Vec::new(),
+            suite: Vec::new(),
+            exclude: Vec::new(),
+            shards: 1,
+            timeout: "10s".to_owned(),
+            deadline: None,
+            binary_cache: Some(PathBuf::from(".sandboxer-cache/binary-inputs.json")),
+            log_ignore: Vec::new(),
+            log_level: LogLevel::Output,
+            log_format: LogFormat::Pretty,
+            overrides: PathBuf::from("instance-list.toml"),
+            check: false,
+            api_dump: None,
+            modules: Vec::new(),
+            jobs: 4,
+            dry_run: false,
+            baseline: None,
+            threshold: 10,
+            api_snapshot: PathBuf::from("api.snapshot"),
+            update_api: false,
+            asset_id: None,
+            tested: PathBuf::from("tested.json"),
+            api_url: opencloud::DEFAULT_BASE_URL.to_owned(),
+            verbosity: 0,
+            log_file: None,
+            no_color: false,
+        }
+    }
+}
+
+fn parse_seconds<'a>(name: &str, value: &'a str) -> (&'a str, f64) {
+    let seconds = value.strip_suffix('s').unwrap_or(value);
+    // plain decimals only, which is what the Open Cloud duration format takes
+    let (whole, fraction) = seconds.split_once('.').unwrap_or((seconds, "0"));
+    let digits = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+    match seconds.parse::<f64>() {
+        Ok(s) if digits(whole) && digits(fraction) && s > 0.0 && s.is_finite() => (seconds, s),
+        _ => panic!("Invalid {name} '{value}' (expected seconds, e.g. `30s`)"),
+    }
+}
+
+impl Args {
+    pub fn parse() -> Self {
+        Self::parse_from(args().skip(1))
+    }
+
+    pub fn parse_from<I: IntoIterator<Item = String>>(iter: I) -> Self {
+        let mut parsed = Self::default();
+        let mut iter = iter.into_iter().peekable();
+
+        if let Some(command) = iter.next_if(|arg| !arg.starts_with('-')) {
+            parsed.command = match command.as_str() {
+                "test" => Command::Test,
+                "instance-list" => Command::InstanceList,
+                "audit" => Command::Audit,
+                "mutate" => Command::Mutate,
+                "watch" => Command::Watch,
+                "bench" => Command::Bench,
+                "publish" => Command::Publish,
+                _ => panic!("Unknown command '{command}'\n\n{USAGE}"),
+            };
+        }
+
+        while let Some(arg) = iter.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value.to_owned())),
+                _ => (arg.as_str(), None),
+            };
+
+            let mut value = || {
+                inline
+                    .clone()
+                    .or_else(|| iter.next())
+                    .unwrap_or_else(|| panic!("Missing value for {flag}"))
+            };
+
+            match flag {
+                "--project" => parsed.project = PathBuf::from(value()),
+                "--policy" => parsed.policy = Some(PathBuf::from(value())),
+                "--xml" => parsed.xml = true,
+                "--reproducible" => parsed.reproducible = true,
+                "--build-version" => parsed.build_version = Some(value()),
+                "--license" => parsed.license = LicenseMode::parse(&value()),
+                "--minify" => parsed.minify = true,
+                "--coverage" => parsed.coverage = true,
+                "--filter" => parsed.filter.push(value()),
+                "--suite" => parsed.suite.push(value()),
+                "--exclude" => parsed.exclude.push(value()),
+                "--shards" => {
+                    let value = value();
+                    parsed.shards = match value.parse() {
+                        Ok(0) | Err(_) => panic!("Invalid shard count '{value}'"),
+                        Ok(n) => n,
+                    };
+                }
+                "--timeout" => {
+                    parsed.timeout = format!("{}s", parse_seconds("timeout", &value()).0);
+                }
+                "--deadline" => {
+                    let value = value();
+                    let (_, seconds) = parse_seconds("deadline", &value);
+                    parsed.deadline = Some(Duration::from_secs_f64(seconds));
+                }
+                "--binary-cache" => parsed.binary_cache = Some(PathBuf::from(value())),
+                "--no-binary-cache" => parsed.binary_cache = None,
+                "--log-ignore" => parsed.log_ignore.push(value()),
+                "--log-level" => parsed.log_level = LogLevel::parse(&value()),
+                "--log-format" => parsed.log_format = LogFormat::parse(&value()),
+                "--overrides" => parsed.overrides = PathBuf::from(value()),
+                "--check" => parsed.check = true,
+                "--api-dump" => parsed.api_dump = Some(PathBuf::from(value())),
+                "--module" => parsed.modules.push(value()),
+                "--jobs" => {
+                    let value = value();
+                    parsed.jobs = match value.parse() {
+                        Ok(0) | Err(_) => panic!("Invalid job count '{value}'"),
+                        Ok(n) => n,
+                    };
+                }
+                "--dry-run" => parsed.dry_run = true,
+                "--api-snapshot" => parsed.api_snapshot = PathBuf::from(value()),
+                "--update-api" => parsed.update_api = true,
+                "--asset-id" => {
+                    let value = value();
+                    parsed.asset_id = Some(
+                        value
+                            .parse()
+                            .unwrap_or_else(|_| panic!("Invalid asset ID '{value}'")),
+                    );
+                }
+                "--tested" => parsed.tested = PathBuf::from(value()),
+                "--api-url" => parsed.api_url = value().trim_end_matches('/').to_owned(),
+                "-v" | "--verbose" => parsed.verbosity = parsed.verbosity.saturating_add(1),
+                "-vv" => parsed.verbosity = parsed.verbosity.saturating_add(2),
+                "-q" | "--quiet" => parsed.verbosity = parsed.verbosity.saturating_sub(1),
+                "-qq" => parsed.verbosity = parsed.verbosity.saturating_sub(2),
+                "--log-file" => parsed.log_file = Some(PathBuf::from(value())),
+                "--baseline" => parsed.baseline = Some(PathBuf::from(value())),
+                "--threshold" => {
+                    let value = value();
+                    parsed.threshold = value.trim_end_matches('%').parse().unwrap_or_else(|_| {
+                        panic!("Invalid threshold '{value}' (expected a percentage, e.g. `10`)")
+                    });
+                }
+                "--no-color" => parsed.no_color = true,
+                "-h" | "--help" => {
+                    eprintln!("{USAGE}");
+                    std::process::exit(0);
+                }
+                _ => panic!("Unknown argument '{arg}'\n\n{USAGE}"),
+            }
+        }
+
+        parsed
+    }
+
+    /// The sandbox policy; `sandboxer.toml` next to the project file unless
+    /// `--policy` is given.
+    pub fn policy_path(&self) -> PathBuf {
+        self.policy.clone().unwrap_or_else(|| {
+            let base = self.project.parent().unwrap_or(Path::new("."));
+            base.join("sandboxer.toml")
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        Args::parse_from(args.iter().map(ToString::to_string))
+    }
+
+    #[test]
+    fn parses_repeated_and_inline_values() {
+        let args = parse(&[
+            "--filter",
+            "HttpService",
+            "--suite=Sandboxer",
+            "--suite",
+            "Integration",
+            "--exclude=Performance",
+        ]);
+        assert_eq!(
+            args,
+            Args {
+                filter: vec!["HttpService".into()],
+                suite: vec!["Sandboxer".into(), "Integration".into()],
+                exclude: vec!["Performance".into()],
+                ..Args::default()
+            }
+        );
+    }
+
+    #[test]
+    fn parses_shards_and_timeout() {
+        let args = parse(&["--shards", "3", "--timeout=30"]);
+        assert_eq!(args.shards, 3);
+        assert_eq!(args.timeout, "30s");
+        assert_eq!(parse(&["--timeout", "2.5s"]).timeout, "2.5s");
+    }
+
+    #[test]
+    fn parses_deadline() {
+        assert_eq!(
+            parse(&["--deadline", "90s"]).deadline,
+            Some(Duration::from_secs(90))
+        );
+        assert_eq!(parse(&[]).deadline, None);
+    }
+
+    #[test]
+    #[should_panic = "Invalid shard count '0'"]
+    fn rejects_zero_shards() {
+        parse(&["--shards", "0"]);
+    }
+
+    #[test]
+    #[should_panic = "Invalid timeout 'soon'"]
+    fn rejects_invalid_timeout() {
+        parse(&["--timeout", "soon"]);
+    }
+
+    #[test]
+    fn rejects_non_decimal_seconds() {
+        for value in ["1e3", "+5", "inf", ".5", "5.", "-1", "0"] {
+            let result = std::panic::catch_unwind(|| parse(&["--deadline", value]));
+            assert!(result.is_err(), "accepted {value:?}");
+        }
+    }
+
+    #[test]
+    fn parses_log_options() {
+        let args = parse(&[
+            "--log-ignore",
+            "^Loading",
+            "--log-level=warn",
+            "--log-format",
+            "json",
+            "--no-color",
+        ]);
+        assert_eq!(args.log_ignore, ["^Loading"]);
+        assert_eq!(args.log_level, LogLevel::Warning);
+        assert_eq!(args.log_format, LogFormat::Json);
+        assert!(args.no_color);
+    }
+
+    #[test]
+    fn parses_binary_cache() {
+        assert!(parse(&[]).binary_cache.is_some());
+        assert_eq!(
+            parse(&["--binary-cache", "cache.json"]).binary_cache,
+            Some(PathBuf::from("cache.json"))
+        );
+        assert_eq!(parse(&["--no-binary-cache"]).binary_cache, None);
+    }
+
+    #[test]
+    fn parses_command() {
+        assert_eq!(parse(&[]).command, Command::Test);
+        let args = parse(&["instance-list", "--check"]);
+        assert_eq!(args.command, Command::InstanceList);
+        assert!(args.check);
+    }
+
+    #[test]
+    fn parses_bench_options() {
+        let args = parse(&["bench", "--baseline", "main.json", "--threshold=25%"]);
+        assert_eq!(args.command, Command::Bench);
+        assert_eq!(args.baseline, Some(PathBuf::from("main.json")));
+        assert_eq!(args.threshold, 25);
+    }
+
+    #[test]
+    fn parses_publish_options() {
+        let args = parse(&[
+            "publish",
+            "--asset-id=1234",
+            "--api-url",
+            "http://127.0.0.1:8080/",
+        ]);
+        assert_eq!(args.command, Command::Publish);
+        assert_eq!(args.asset_id, Some(1234));
+        assert_eq!(args.api_url, "http://127.0.0.1:8080");
+        assert_eq!(args.tested, PathBuf::from("tested.json"));
+    }
+
+    #[test]
+    fn parses_mutate_options() {
+        let args = parse(&[
+            "mutate",
+            "--module",
+            "InstanceList",
+            "--jobs=8",
+            "--dry-run",
+        ]);
+        assert_eq!(args.command, Command::Mutate);
+        assert_eq!(args.modules, ["InstanceList"]);
+        assert_eq!(args.jobs, 8);
+        assert!(args.dry_run);
+    }
+
+    #[test]
+    #[should_panic = "Unknown command 'deploy'"]
+    fn rejects_unknown_command() {
+        parse(&["deploy"]);
+    }
+
+    #[test]
+    #[should_panic = "Missing value for --filter"]
+    fn rejects_missing_value() {
+        parse(&["--filter"]);
+    }
+
+    #[test]
+    #[should_panic = "Unknown argument '--bogus'"]
+    fn rejects_unknown_argument() {
+        parse(&["--bogus"]);
+    }
+}
@@ -0,0 +1,479 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::Write,
+    fs::read_to_string,
+    path::Path,
+    sync::LazyLock,
+};
+
+use rbx_reflection::{ClassTag, ReflectionDatabase};
+use regex::Regex;
+use serde::Deserialize;
+
+use crate::{args::Args, policy::Policy};
+
+/// Member names that usually mean the member talks to the network, persists
+/// data or affects other players. Matching is a heuristic; the report is
+/// meant to be reviewed, not enforced.
+static SIDE_EFFECT_NAME: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        "Async$|^(Award|Ban|Kick|Load|Post|Prompt|Publish|Report|Save|Send|Teleport|Unban)[A-Z]|Badge|Http|Purchase|Secret",
+    )
+    .expect("Invalid side effect pattern")
+});
+
+/// The parts of a Roblox API dump (`Full-API-Dump.json`) needed for the
+/// audit. The bundled reflection database only describes properties, so
+/// members, security levels and `Yields` tags come from here.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiDump {
+    pub classes: Vec<ApiClass>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiClass {
+    pub name: String,
+    /// `<<<ROOT>>>` for `Instance`.
+    #[serde(default)]
+    pub superclass: String,
+    #[serde(default)]
+    pub members: Vec<ApiMember>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "PascalCase")]
+pub struct ApiMember {
+    pub member_type: String,
+    pub name: String,
+    #[serde(default)]
+    pub security: Option<ApiSecurity>,
+    /// Mostly strings, but some tags are objects (`PreferredDescriptorName`).
+    #[serde(default)]
+    pub tags: Vec<serde_json::Value>,
+}
+
+/// Functions, events and callbacks have one security level; properties have
+/// one for reading and one for writing.
+#[derive(Deserialize, Debug)]
+#[serde(untagged)]
+pub enum ApiSecurity {
+    Single(String),
+    ReadWrite {
+        #[serde(rename = "Read")]
+        read: String,
+        #[serde(rename = "Write")]
+        write: String,
+    },
+}
+
+impl ApiMember {
+    /// Whether scripts without special permissions can use the member (for
+    /// properties, read or write it).
+    pub fn is_scriptable(&self) -> bool {
+        let levels = match &self.security {
+            Some(ApiSecurity::Single(level)) => vec![level.as_str()],
+            Some(ApiSecurity::ReadWrite { read, write }) => vec![read.as_str(), write.as_str()],
+            None => vec!["None"],
+        };
+        levels.contains(&"None") && !self.tags.iter().any(|tag| tag == "NotScriptable")
+    }
+}
+
+impl ApiDump {
+    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let source = read_to_string(path)
+            .unwrap_or_else(|_| panic!("Failed to read API dump {}", path.display()));
+        serde_json::from_str(&source)
+            .unwrap_or_else(|e| panic!("Invalid API dump {}: {e}", path.display()))
+    }
+}
+
+/// A member that needs review, because it is not hooked and matched at least
+/// one of the audit rules.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Finding {
+    pub class: String,
+    /// The allowed services the class is reachable from.
+    pub services: Vec<String>,
+    pub kind: String,
+    pub member: String,
+    pub reasons: Vec<String>,
+}
+
+/// A class reachable from `InstanceList.Allow`, with the services it is
+/// reached from.
+struct Reachable<'a> {
+    services: BTreeSet<&'a str>,
+    /// Members denied by `DeniedMembers`. Hooks only apply to the service
+    /// itself, not to its descendants or to members it inherits.
+    hooked: BTreeSet<&'a str>,
+}
+
+/// Classes scripts cannot create that the engine puts under a service, so
+/// they are only reachable through the descendants of that service.
+const ENGINE_DESCENDANTS: [(&str, &[&str]); 4] = [
+    ("Players", &["PlayerGui", "PlayerScripts"]),
+    (
+        "StarterPlayer",
+        &["StarterCharacterScripts", "StarterPlayerScripts"],
+    ),
+    (
+        "TextChatService",
+        &[
+            "BubbleChatConfiguration",
+            "ChatInputBarConfiguration",
+            "ChatWindowConfiguration",
+            "TextSource",
+        ],
+    ),
+    ("Workspace", &["Terrain"]),
+];
+
+/// The classes that can be reached from the allowed services, including
+/// superclasses so that inherited members are audited once, by the class
+/// that declares them. Like `InstanceList.instanceAllowed`, explicitly
+/// disallowed services (and their descendants, unless allowed) are left out.
+/// Descendants are the classes scripts can create and parent there, and the
+/// classes the engine creates under the service (`ENGINE_DESCENDANTS`).
+fn reachable_classes<'a>(
+    db: &'a ReflectionDatabase,
+    policy: &'a Policy,
+) -> BTreeMap<&'a str, Reachable<'a>> {
+    let list = &policy.instance_list;
+    let excluded: BTreeSet<_> = list
+        .disallowed_classes
+        .iter()
+        .chain(&list.forbidden_classes)
+        .map(String::as_str)
+        .collect();
+    let creatable: Vec<_> = db
+        .classes
+        .values()
+        .filter(|class| {
+            !class.tags.contains(&ClassTag::Service)
+                && !class.tags.contains(&ClassTag::NotCreatable)
+                && !excluded.contains(class.name.as_ref())
+        })
+        .map(|class| class.name.as_ref())
+        .collect();
+    let hooks = |service: &str| -> BTreeSet<&'a str> {
+        policy
+            .denied_members
+            .get(service)
+            .map(|members| members.keys().map(String::as_str).collect())
+            .unwrap_or_default()
+    };
+
+    let mut reachable = BTreeMap::new();
+    for (service, &descendants) in &list.allow {
+        let class = if service == "game" {
+            "DataModel"
+        } else {
+            service.as_str()
+        };
+
+        let mut roots = Vec::new();
+        if !list.explicit_disallow.contains_key(service) {
+            roots.push((class, hooks(service)));
+        }
+        if descendants {
+            // the services under the DataModel, and what the engine puts there
+            let mut containers = vec![class];
+            if service == "game" {
+                for child in db.classes.values() {
+                    let name = child.name.as_ref();
+                    if !child.tags.contains(&ClassTag::Service) {
+                        continue;
+                    }
+                    match list.explicit_disallow.get(name) {
+                        None => {
+                            roots.push((name, hooks(name)));
+                            containers.push(name);
+                        }
+                        Some(true) => containers.push(name),
+                        Some(false) => {}
+                    }
+                }
+            }
+            roots.extend(creatable.iter().map(|&c| (c, BTreeSet::new())));
+            for (container, classes) in ENGINE_DESCENDANTS {
+                if containers.contains(&container) {
+                    roots.extend(
+                        classes
+                            .iter()
+                            .filter(|c| !excluded.contains(*c))
+                            .map(|&c| (c, BTreeSet::new())),
+                    );
+                }
+            }
+        }
+
+        for (root, hooked) in roots {
+            let mut next = Some(root);
+            while let Some(name) = next {
+                let entry = reachable.entry(name).or_insert_with(|| Reachable {
+                    services: BTreeSet::new(),
+                    hooked: BTreeSet::new(),
+                });
+                entry.services.insert(service.as_str());
+                // inherited members are shared with classes that are not hooked
+                if name == root {
+                    entry.hooked.extend(&hooked);
+                }
+                next = db
+                    .classes
+                    .get(name)
+                    .and_then(|class| class.superclass.as_deref());
+            }
+        }
+    }
+    reachable
+}
+
+fn security_level(security: &Option<ApiSecurity>) -> Option<String> {
+    let levels = match security {
+        Some(ApiSecurity::Single(level)) => vec![level.as_str()],
+        Some(ApiSecurity::ReadWrite { read, write }) => vec![read.as_str(), write.as_str()],
+        None => Vec::new(),
+    };
+    let mut levels: Vec<_> = levels.into_iter().filter(|&l| l != "None").collect();
+    levels.dedup();
+    (!levels.is_empty()).then(|| levels.join("/"))
+}
+
+/// Audits the members in `dump` of the classes reachable from `policy`'s
+/// allowed services.
+pub fn audit(db: &ReflectionDatabase, dump: &ApiDump, policy: &Policy) -> Vec<Finding> {
+    let dump_classes: BTreeMap<_, _> = dump.classes.iter().map(|c| (c.name.as_str(), c)).collect();
+
+    let mut findings = Vec::new();
+    for (class, reachable) in reachable_classes(db, policy) {
+        let Some(api_class) = dump_classes.get(class) else {
+            continue;
+        };
+        for member in &api_class.members {
+            if reachable.hooked.contains(member.name.as_str()) {
+                continue;
+            }
+            let mut reasons = Vec::new();
+            if let Some(security) = security_level(&member.security) {
+                reasons.push(format!("security {security}"));
+            }
+            if member.tags.iter().any(|tag| tag == "Yields") {
+                reasons.push("yields".to_owned());
+            }
+            if SIDE_EFFECT_NAME.is_match(&member.name) {
+                reasons.push("side effect name".to_owned());
+            }
+            if !reasons.is_empty() {
+                findings.push(Finding {
+                    class: class.to_owned(),
+                    services: reachable.services.iter().map(|&s| s.to_owned()).collect(),
+                    kind: member.member_type.clone(),
+                    member: member.name.clone(),
+                    reasons,
+                });
+            }
+        }
+    }
+    findings.sort_unstable_by(|a, b| (&a.class, &a.member).cmp(&(&b.class, &b.member)));
+    findings
+}
+
+/// Formats findings as a Markdown report, grouped by declaring class.
+pub fn report(findings: &[Finding], source: &str) -> String {
+    let mut out = format!("# Sandbox surface audit\n\nMembers from {source}.\n");
+    let mut class = "";
+    for finding in findings {
+        if finding.class != class {
+            class = &finding.class;
+            let _ = write!(
+                out,
+                "\n## {class}\n\nReachable from {}.\n\n",
+                finding.services.join(", ")
+            );
+        }
+        let _ = writeln!(
+            out,
+            "- [ ] {} `{}`: {}",
+            finding.kind,
+            finding.member,
+            finding.reasons.join(", ")
+        );
+    }
+    if findings.is_empty() {
+        out.push_str("\nNo unhooked members need review.\n");
+    }
+    out
+}
+
+/// `sandboxer-builder audit`: prints a Markdown report of unhooked members of
+/// the classes reachable from `InstanceList.Allow`.
+pub fn run(args: &Args) {
+    let Some(path) = &args.api_dump else {
+        panic!(
+            "audit needs a Full-API-Dump.json (--api-dump); the bundled reflection database has no functions, events or security levels"
+        );
+    };
+    let db = rbx_reflection_database::get_bundled();
+    let policy_path = args.policy_path();
+    let policy = Policy::read(&policy_path);
+    let dump = ApiDump::read(path);
+    policy
+        .check_members(Some(&dump))
+        .unwrap_or_else(|e| panic!("Invalid policy file {}: {e}", policy_path.display()));
+
+    let source = format!("the API dump at {}", path.display());
+    let findings = audit(db, &dump, &policy);
+    print!("{}", report(&findings, &source));
+    info!("{} members need review", findings.len());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> &'static ReflectionDatabase<'static> {
+        rbx_reflection_database::get_bundled()
+    }
+
+    const DUMP: &str = r#"{"Classes": [
+        {"Name": "Players", "Members": [
+            {"MemberType": "Function", "Name": "BanAsync", "Security": "None", "Tags": ["Yields"]},
+            {"MemberType": "Function", "Name": "GetPlayers", "Security": "None"},
+            {"MemberType": "Function", "Name": "Chat", "Security": "PluginSecurity"},
+            {"MemberType": "Function", "Name": "GetUserIdFromNameAsync", "Security": "None", "Tags": ["Yields", {"PreferredDescriptorName": "x"}]},
+            {"MemberType": "Property", "Name": "MaxPlayers", "Security": {"Read": "None", "Write": "None"}},
+            {"MemberType": "Property", "Name": "PreferredPlayers", "Security": {"Read": "None", "Write": "LocalUserSecurity"}}
+        ]}
+    ]}"#;
+
+    #[test]
+    fn reports_unhooked_members_from_api_dump() {
+        let dump: ApiDump = serde_json::from_str(DUMP).unwrap();
+        let policy = Policy::parse(
+            "[InstanceList.Allow]\nPlayers = false\n\
+             [DeniedMembers.Players]\nBanAsync = \"no\"",
+        )
+        .unwrap();
+
+        let findings: Vec<_> = audit(db(), &dump, &policy)
+            .into_iter()
+            .filter(|f| f.class == "Players")
+            .map(|f| (f.member, f.reasons.join(", ")))
+            .collect();
+        assert_eq!(
+            findings,
+            [
+                ("Chat".to_owned(), "security PluginSecurity".to_owned()),
+                (
+                    "GetUserIdFromNameAsync".to_owned(),
+                    "yields, side effect name".to_owned()
+                ),
+                (
+                    "PreferredPlayers".to_owned(),
+                    "security LocalUserSecurity".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn audits_inherited_and_descendant_classes() {
+        let policy = Policy::parse("[InstanceList.Allow]\nWorkspace = true").unwrap();
+        let reachable = reachable_classes(db(), &policy);
+
+        assert!(reachable.contains_key("WorldRoot"));
+        assert!(reachable.contains_key("Instance"));
+        assert!(reachable.contains_key("Part"));
+        // not creatable by scripts, but the engine puts it in Workspace
+        assert!(reachable.contains_key("Terrain"));
+        assert!(!reachable.contains_key("PlayerGui"));
+        assert!(!reachable.contains_key("Players"));
+
+        let policy = Policy::parse(
+            "[InstanceList]\nDisallowedClasses = [\"Fire\"]\n[InstanceList.Allow]\nWorkspace = true",
+        )
+        .unwrap();
+        assert!(reachable.contains_key("Fire"));
+        assert!(!reachable_classes(db(), &policy).contains_key("Fire"));
+    }
+
+    #[test]
+    fn explicitly_disallowed_services_are_not_reachable() {
+        let policy = Policy::parse(
+            "[InstanceList.Allow]\ngame = true\n\
+             [InstanceList.ExplicitDisallow]\nPlayers = false\nWorkspace = true",
+        )
+        .unwrap();
+        let reachable = reachable_classes(db(), &policy);
+
+        assert!(reachable.contains_key("DataModel"));
+        assert!(reachable.contains_key("Lighting"));
+        assert!(!reachable.contains_key("Players"));
+        assert!(!reachable.contains_key("PlayerGui"));
+        // only the service itself is disallowed
+        assert!(!reachable.contains_key("Workspace"));
+        assert!(reachable.contains_key("Terrain"));
+
+        let policy = Policy::parse(
+            "[InstanceList.Allow]\nLighting = true\n\
+             [InstanceList.ExplicitDisallow]\nLighting = true",
+        )
+        .unwrap();
+        let reachable = reachable_classes(db(), &policy);
+        assert!(!reachable.contains_key("Lighting"));
+        assert!(reachable.contains_key("Sky"));
+    }
+
+    #[test]
+    fn engine_descendants_are_not_creatable() {
+        for (service, classes) in ENGINE_DESCENDANTS {
+            assert!(db().classes[service].tags.contains(&ClassTag::Service));
+            for &class in classes {
+                assert!(
+                    db().classes[class].tags.contains(&ClassTag::NotCreatable),
+                    "{class}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn repository_policy_reaches_http_service() {
+        // allowed at runtime for GenerateGUID, with its network members hooked
+        let policy = Policy::read("../sandboxer.toml");
+        let reachable = reachable_classes(db(), &policy);
+        assert!(reachable["HttpService"].hooked.contains("GetAsync"));
+    }
+
+    #[test]
+    fn hooks_only_cover_the_service() {
+        let policy = Policy::parse(
+            "[InstanceList.Allow]\nPlayers = false\n\
+             [DeniedMembers.Players]\nBanAsync = \"no\"",
+        )
+        .unwrap();
+        let reachable = reachable_classes(db(), &policy);
+        assert!(reachable["Players"].hooked.contains("BanAsync"));
+        assert!(reachable["Instance"].hooked.is_empty());
+    }
+
+    #[test]
+    fn formats_report() {
+        let findings = [Finding {
+            class: "Players".to_owned(),
+            services: vec!["Players".to_owned()],
+            kind: "Function".to_owned(),
+            member: "KickAsync".to_owned(),
+            reasons: vec!["yields".to_owned()],
+        }];
+        assert_eq!(
+            report(&findings, "a test"),
+            "# Sandbox surface audit\n\nMembers from a test.\n\n## Players\n\nReachable from Players.\n\n- [ ] Function `KickAsync`: yields\n"
+        );
+    }
+}
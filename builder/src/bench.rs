@@ -0,0 +1,286 @@
+use std::{
+    fs::{self, read_dir},
+    path::Path,
+    process::exit,
+    sync::Mutex,
+    time::Instant,
+};
+
+use crate::{
+    args::Args,
+    binary_cache::BinaryCache,
+    build_sandboxer_dom, cancel,
+    json::{BenchmarkMeasurement, LuauExecutionTaskOutput, LuauExecutionTaskState},
+    logs::LogOptions,
+    module_script_with_source, open_cloud, print_results, read_source, run_shard, script_paths,
+    shard::Shard,
+    stream_and_print_logs, test_selection_attributes,
+};
+use rbx_dom_weak::{InstanceBuilder, WeakDom};
+
+const BENCH_DIR: &str = "./builder/src/bench";
+pub const RESULTS_FILE: &str = "bench.json";
+
+/// How many times slower the sandboxed version is than the raw one.
+/// Comparing ratios instead of times keeps runs on different machines
+/// comparable.
+fn overhead(measurement: &BenchmarkMeasurement) -> f64 {
+    measurement.sandboxed / measurement.raw
+}
+
+/// A measurement next to the one of the same benchmark in the baseline.
+#[derive(Debug, PartialEq)]
+pub struct Comparison<'a> {
+    pub current: &'a BenchmarkMeasurement,
+    pub baseline: Option<&'a BenchmarkMeasurement>,
+}
+
+impl Comparison<'_> {
+    /// Relative change of the overhead against the baseline, e.g. `0.1` for
+    /// 10% slower.
+    pub fn change(&self) -> Option<f64> {
+        self.baseline
+            .map(|baseline| overhead(self.current) / overhead(baseline) - 1.0)
+    }
+
+    /// Whether the overhead grew by more than `threshold` percent.
+    pub fn regressed(&self, threshold: f64) -> bool {
+        self.change()
+            .is_some_and(|change| change * 100.0 > threshold)
+    }
+}
+
+impl std::fmt::Display for Comparison<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let current = self.current;
+        write!(
+            f,
+            "{} {}: raw {:.1} ns, sandboxed {:.1} ns, {:.2}x",
+            current.suite,
+            current.name,
+            current.raw,
+            current.sandboxed,
+            overhead(current)
+        )?;
+        match (self.baseline, self.change()) {
+            (Some(baseline), Some(change)) => write!(
+                f,
+                " (baseline {:.2}x, {:+.1}%)",
+                overhead(baseline),
+                change * 100.0
+            ),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Pairs every measurement with the baseline measurement of the same
+/// benchmark, if there is one.
+pub fn compare<'a>(
+    current: &'a [BenchmarkMeasurement],
+    baseline: &'a [BenchmarkMeasurement],
+) -> Vec<Comparison<'a>> {
+    current
+        .iter()
+        .map(|current| Comparison {
+            current,
+            baseline: baseline
+                .iter()
+                .find(|b| b.suite == current.suite && b.name == current.name),
+        })
+        .collect()
+}
+
+pub fn read_results(path: &Path) -> Vec<BenchmarkMeasurement> {
+    let json = fs::read_to_string(path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+    serde_json::from_str(&json)
+        .unwrap_or_else(|e| panic!("Invalid benchmark results in {}: {e}", path.display()))
+}
+
+/// Reads every `*.bench.luau` module in `builder/src/bench`, sorted by name.
+fn read_benchmark_modules() -> Vec<(String, String)> {
+    let mut modules: Vec<_> = read_dir(BENCH_DIR)
+        .expect("Failed to read benchmarks directory")
+        .filter_map(|entry| {
+            let path = entry.expect("Failed to read benchmark module").path();
+            let name = path.file_name()?.to_str()?.strip_suffix(".luau")?;
+            name.ends_with(".bench")
+                .then(|| (name.to_owned(), read_source(path.to_string_lossy())))
+        })
+        .collect();
+    modules.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    modules
+}
+
+fn build_bench_rbxm(latest_rbxm: &WeakDom, args: &Args, modules: Vec<(String, String)>) -> Vec<u8> {
+    let modules = modules
+        .into_iter()
+        .map(|(name, source)| module_script_with_source(&name, source));
+
+    let mut dom = WeakDom::new(
+        InstanceBuilder::new("Model")
+            .with_name("Sandboxer-Bench")
+            .with_child(
+                InstanceBuilder::with_property_capacity("ModuleScript", 2)
+                    .with_name("RunBenchmarks")
+                    .with_property("Source", read_source(format!("{BENCH_DIR}/init.luau")))
+                    .with_property("Attributes", test_selection_attributes(args))
+                    .with_child(
+                        InstanceBuilder::new("Folder")
+                            .with_name("benchmarks")
+                            .with_children(modules),
+                    ),
+            ),
+    );
+
+    let root = dom.root_ref();
+    let sandbox_root = latest_rbxm.clone_into_external(latest_rbxm.root_ref(), &mut dom);
+    dom.transfer_within(sandbox_root, root);
+
+    let mut buf = Vec::with_capacity(64 * 1000);
+    rbx_binary::to_writer(&mut buf, &dom, &[root]).expect("Failed to compile rbxm file");
+    buf
+}
+
+pub fn run(args: &Args) {
+    let log_options = LogOptions::new(&args.log_ignore, args.log_level, args.log_format);
+    let baseline = args.baseline.as_deref().map(read_results);
+    let build = build_sandboxer_dom(args);
+    let modules = read_benchmark_modules();
+    let runner = [("RunBenchmarks".to_owned(), format!("{BENCH_DIR}/init.luau"))]
+        .into_iter()
+        .chain(modules.iter().map(|(name, _)| {
+            (
+                format!("RunBenchmarks.benchmarks.{name}"),
+                format!("{BENCH_DIR}/{name}.luau"),
+            )
+        }));
+    let scripts = script_paths("Sandboxer-Bench", &build, runner);
+    let buf = build_bench_rbxm(&build.dom, args, modules);
+
+    let client = open_cloud(args);
+    cancel::install_handler(client.clone());
+    let deadline = args.deadline.map(|d| Instant::now() + d);
+    let shard = Shard { index: 0, count: 1 };
+    let cache = Mutex::new(BinaryCache::open(args.binary_cache.as_deref()));
+
+    info!("Running the benchmarks...");
+    let (id, response) = run_shard(&client, &buf, &args.timeout, shard, deadline, &cache);
+    stream_and_print_logs(&client, &id, shard, &log_options, &scripts);
+
+    let result = match (response.state, response.output) {
+        (LuauExecutionTaskState::Complete, Some(LuauExecutionTaskOutput { results: [result] })) => {
+            result
+        }
+        (LuauExecutionTaskState::Complete, None) => {
+            panic!("Luau execution session for the benchmarks has no output")
+        }
+        (state, _) => match response.error {
+            Some(err) => panic!(
+                "Luau execution session for the benchmarks failed: {}",
+                err.message
+            ),
+            None => panic!(
+                "Luau execution session for the benchmarks did not finish (state: {state:?})"
+            ),
+        },
+    };
+
+    let json = serde_json::to_string_pretty(&result.benchmarks)
+        .expect("Failed to serialize benchmark results");
+    fs::write(RESULTS_FILE, json).unwrap_or_else(|e| panic!("Failed to write {RESULTS_FILE}: {e}"));
+    info!(
+        "Wrote {RESULTS_FILE} ({} benchmarks)",
+        result.benchmarks.len()
+    );
+
+    let baseline = baseline.unwrap_or_default();
+    let mut regressions = 0;
+    for comparison in compare(&result.benchmarks, &baseline) {
+        println!("{comparison}");
+        if args.baseline.is_some() && comparison.baseline.is_none() {
+            warn!(
+                "{} {} is not in the baseline",
+                comparison.current.suite, comparison.current.name
+            );
+        }
+        if comparison.regressed(f64::from(args.threshold)) {
+            regressions += 1;
+            warn!("Regression: {comparison}");
+        }
+    }
+    if args.baseline.is_some() {
+        info!(
+            "{regressions} of {} benchmarks regressed by more than {}%",
+            result.benchmarks.len(),
+            args.threshold
+        );
+    }
+
+    print_results(&result);
+    exit(i32::from(!result.success || regressions > 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(name: &str, raw: f64, sandboxed: f64) -> BenchmarkMeasurement {
+        BenchmarkMeasurement {
+            suite: "Sandbox".to_owned(),
+            name: name.to_owned(),
+            iterations: 1024,
+            raw,
+            sandboxed,
+        }
+    }
+
+    #[test]
+    fn flags_overhead_regressions() {
+        let baseline = [
+            measurement("property read", 10.0, 40.0),
+            measurement("method call", 20.0, 60.0),
+        ];
+        // a slower machine, but the same overhead
+        let current = [
+            measurement("property read", 20.0, 80.0),
+            measurement("method call", 20.0, 72.0),
+            measurement("signal connect", 100.0, 300.0),
+        ];
+        let comparisons = compare(&current, &baseline);
+
+        assert_eq!(comparisons[0].change(), Some(0.0));
+        assert!(!comparisons[0].regressed(10.0));
+        assert!(comparisons[1].regressed(10.0));
+        assert!(!comparisons[1].regressed(25.0));
+        assert_eq!(comparisons[2].baseline, None);
+        assert!(!comparisons[2].regressed(0.0));
+
+        assert_eq!(
+            comparisons[1].to_string(),
+            "Sandbox method call: raw 20.0 ns, sandboxed 72.0 ns, 3.60x (baseline 3.00x, +20.0%)"
+        );
+        assert_eq!(
+            comparisons[2].to_string(),
+            "Sandbox signal connect: raw 100.0 ns, sandboxed 300.0 ns, 3.00x"
+        );
+    }
+
+    #[test]
+    fn reads_task_results() {
+        let json = r#"{
+            "suites": 1, "total": 1, "passed": 1, "failed": 0, "success": true, "time": 1.5,
+            "benchmarks": [{"suite": "Sandbox", "name": "property read", "iterations": 4096,
+                            "raw": 12.5, "sandboxed": 51.0}]
+        }"#;
+        let result: crate::json::LuauExecutionTaskResult = serde_json::from_str(json).unwrap();
+        assert_eq!(
+            result.benchmarks,
+            [BenchmarkMeasurement {
+                iterations: 4096,
+                ..measurement("property read", 12.5, 51.0)
+            }]
+        );
+    }
+}
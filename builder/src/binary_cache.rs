@@ -0,0 +1,193 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+/// How long an uploaded binary input is reused. Inputs the API has already
+/// deleted are uploaded again when the task is rejected, so this only has
+/// to be a reasonable guess.
+pub const TTL: Duration = Duration::from_secs(24 * 60 * 60);
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+struct Entry {
+    /// Binary input path returned by the API.
+    path: String,
+    /// Unix time of the upload, in seconds.
+    uploaded: u64,
+}
+
+/// Binary inputs uploaded by earlier runs, keyed by [`key`]. Every change is
+/// written to disk right away, so runs that are cancelled halfway still keep
+/// their uploads.
+#[derive(Debug, Default)]
+pub struct BinaryCache {
+    /// `None` when caching is disabled.
+    file: Option<PathBuf>,
+    entries: BTreeMap<String, Entry>,
+}
+
+/// The cache key of an upload: binary inputs belong to a universe and are
+/// only run in one place, so those are part of the key along with the
+/// SHA-256 of the uploaded bytes.
+pub fn key(universe_id: u64, place_id: u64, hash: &str) -> String {
+    format!("{universe_id}/{place_id}/{hash}")
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+impl BinaryCache {
+    /// Opens the cache at `file`; a missing or unreadable file is an empty
+    /// cache. With no file, nothing is cached.
+    pub fn open(file: Option<&Path>) -> Self {
+        let Some(file) = file else {
+            return Self::default();
+        };
+        let entries = match fs::read_to_string(file) {
+            Ok(json) => serde_json::from_str(&json).unwrap_or_else(|e| {
+                warn!(
+                    "Ignoring invalid binary input cache {}: {e}",
+                    file.display()
+                );
+                BTreeMap::new()
+            }),
+            Err(_) => BTreeMap::new(),
+        };
+        Self {
+            file: Some(file.to_owned()),
+            entries,
+        }
+    }
+
+    fn get_at(&self, hash: &str, now: u64) -> Option<&str> {
+        self.entries
+            .get(hash)
+            .filter(|entry| now.saturating_sub(entry.uploaded) < TTL.as_secs())
+            .map(|entry| entry.path.as_str())
+    }
+
+    /// The binary input path of an unexpired upload of the same bytes.
+    pub fn get(&self, hash: &str) -> Option<&str> {
+        self.get_at(hash, now())
+    }
+
+    fn insert_at(&mut self, hash: String, path: String, now: u64) {
+        if self.file.is_none() {
+            return;
+        }
+        self.entries
+            .retain(|_, entry| now.saturating_sub(entry.uploaded) < TTL.as_secs());
+        self.entries.insert(
+            hash,
+            Entry {
+                path,
+                uploaded: now,
+            },
+        );
+        self.save();
+    }
+
+    pub fn insert(&mut self, hash: String, path: String) {
+        self.insert_at(hash, path, now());
+    }
+
+    /// Forgets an upload the API no longer accepts.
+    pub fn remove(&mut self, hash: &str) {
+        if self.entries.remove(hash).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let Some(file) = &self.file else {
+            return;
+        };
+        let json = serde_json::to_string_pretty(&self.entries)
+            .expect("Failed to serialize binary input cache");
+        let written = file
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|()| fs::write(file, json));
+        if let Err(e) = written {
+            warn!("Failed to write binary input cache {}: {e}", file.display());
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A fresh directory under the system temp directory for one test.
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "sandboxer-binary-cache-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    #[test]
+    fn reuses_uploads_until_they_expire() {
+        let dir = temp_dir("expiry");
+        let file = dir.join("cache/binary-inputs.json");
+        let mut cache = BinaryCache::open(Some(&file));
+        cache.insert_at("abc".to_owned(), "binary-inputs/1".to_owned(), 1000);
+
+        let reopened = BinaryCache::open(Some(&file));
+        assert_eq!(reopened.get_at("abc", 1000 + 60), Some("binary-inputs/1"));
+        assert_eq!(reopened.get_at("abc", 1000 + TTL.as_secs()), None);
+        assert_eq!(reopened.get_at("def", 1000), None);
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn prunes_expired_entries_and_removes_rejected_ones() {
+        let dir = temp_dir("prune");
+        let file = dir.join("binary-inputs.json");
+        let mut cache = BinaryCache::open(Some(&file));
+        cache.insert_at("old".to_owned(), "binary-inputs/1".to_owned(), 0);
+        cache.insert_at(
+            "new".to_owned(),
+            "binary-inputs/2".to_owned(),
+            TTL.as_secs(),
+        );
+        assert!(!cache.entries.contains_key("old"));
+
+        cache.remove("new");
+        assert!(BinaryCache::open(Some(&file)).entries.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn keys_by_universe_and_place() {
+        assert_ne!(key(1, 2, "abc"), key(3, 2, "abc"));
+        assert_ne!(key(1, 2, "abc"), key(1, 4, "abc"));
+        assert_eq!(key(1, 2, "abc"), "1/2/abc");
+    }
+
+    #[test]
+    fn disabled_cache_stores_nothing() {
+        let mut cache = BinaryCache::open(None);
+        cache.insert("abc".to_owned(), "binary-inputs/1".to_owned());
+        assert_eq!(cache.get("abc"), None);
+    }
+
+    #[test]
+    fn ignores_invalid_cache_files() {
+        let dir = temp_dir("invalid");
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join("binary-inputs.json");
+        fs::write(&file, "not json").unwrap();
+        assert!(BinaryCache::open(Some(&file)).entries.is_empty());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,76 @@
+use std::{
+    panic::{AssertUnwindSafe, catch_unwind, resume_unwind},
+    process,
+    sync::Mutex,
+};
+
+use opencloud::Client;
+
+/// Paths of execution tasks that have been spawned but have not reached a
+/// terminal state yet.
+static OUTSTANDING: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+fn outstanding() -> std::sync::MutexGuard<'static, Vec<String>> {
+    // a panicking shard should not stop the others from being cancelled
+    OUTSTANDING.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+pub fn track(id: &str) {
+    outstanding().push(id.to_owned());
+}
+
+pub fn untrack(id: &str) {
+    outstanding().retain(|task| task != id);
+}
+
+/// Requests cancellation of a single task. Failures are logged rather than
+/// raised, since this is only ever called while already giving up.
+pub fn cancel_task(client: &Client, id: &str) {
+    match client.cancel_task(id) {
+        Ok(()) => info!("Cancelled {id}"),
+        Err(e) => warn!("Failed to cancel {id}: {e}"),
+    }
+}
+
+pub fn cancel_outstanding(client: &Client) {
+    let tasks = std::mem::take(&mut *outstanding());
+    for id in tasks {
+        cancel_task(client, &id);
+    }
+}
+
+/// Runs `f`, which waits for tasks, on a thread of its own. If it panics,
+/// every outstanding task is cancelled before the panic continues, so the
+/// tasks of other threads don't keep running once one has given up.
+pub fn on_panic<T>(client: &Client, f: impl FnOnce() -> T) -> T {
+    catch_unwind(AssertUnwindSafe(f)).unwrap_or_else(|payload| {
+        cancel_outstanding(client);
+        resume_unwind(payload)
+    })
+}
+
+/// Cancels every outstanding task and exits when the builder receives
+/// Ctrl-C (or SIGTERM/SIGHUP).
+pub fn install_handler(client: Client) {
+    ctrlc::set_handler(move || {
+        warn!("Interrupted; cancelling outstanding Luau execution tasks...");
+        cancel_outstanding(&client);
+        process::exit(130);
+    })
+    .expect("Failed to install Ctrl-C handler");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tracks_outstanding_tasks() {
+        track("universes/1/tasks/a");
+        track("universes/1/tasks/b");
+        untrack("universes/1/tasks/a");
+        assert!(outstanding().iter().any(|id| id == "universes/1/tasks/b"));
+        assert!(!outstanding().iter().any(|id| id == "universes/1/tasks/a"));
+        untrack("universes/1/tasks/b");
+    }
+}
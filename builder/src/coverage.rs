@@ -0,0 +1,291 @@
+use std::{collections::BTreeMap, fmt::Write, path::PathBuf};
+
+use rbx_dom_weak::{WeakDom, types::Variant, ustr};
+
+use crate::{
+    lexer::{Token, tokenize},
+    project::SourceMap,
+};
+
+/// Installed by `main.luau`; instrumented modules call it with their full
+/// name and line count to get their counters.
+const COUNTERS: &str = "shared.__SandboxerCoverage";
+
+/// Keywords that start a statement. Any other keyword at the start of a line
+/// closes a block or continues an expression.
+const STATEMENT_KEYWORDS: [&str; 9] = [
+    "break", "do", "for", "function", "if", "local", "repeat", "return", "while",
+];
+const OTHER_KEYWORDS: [&str; 12] = [
+    "and", "else", "elseif", "end", "false", "in", "nil", "not", "or", "then", "true", "until",
+];
+
+/// Tokens after which the next line is still part of the same statement.
+const CONTINUATIONS: [&str; 46] = [
+    "=", ",", "+", "-", "*", "/", "//", "%", "^", "..", "==", "~=", "<", "<=", ">", ">=", "(", "{",
+    "[", ".", ":", "::", "->", "|", "&", "?", "@", "+=", "-=", "*=", "/=", "//=", "%=", "^=",
+    "..=", "and", "or", "not", "if", "elseif", "while", "until", "in", "return", "local",
+    "function",
+];
+
+/// Instrumented source, with the (1-based) lines that count hits.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Instrumented {
+    pub source: String,
+    pub lines: Vec<u32>,
+}
+
+fn starts_statement(token: &Token) -> bool {
+    token.is_name() && !OTHER_KEYWORDS.contains(&token.text)
+        || STATEMENT_KEYWORDS.contains(&token.text)
+        // `@native function`
+        || token.text == "@"
+}
+
+/// Finds the lines that start a new statement outside of any brackets,
+/// returning each line with the byte offset of its first token.
+fn statement_lines(source: &str) -> Vec<(u32, usize)> {
+    let mut statements = Vec::new();
+    let mut depth = 0usize;
+    // the start of the chunk behaves like the end of a statement
+    let mut prev = "";
+    let mut prev_line = 0;
+
+    for token in tokenize(source) {
+        if token.line != prev_line
+            && depth == 0
+            && starts_statement(&token)
+            && !CONTINUATIONS.contains(&prev)
+        {
+            statements.push((token.line, token.offset));
+        }
+        match token.text {
+            "(" | "{" | "[" => depth += 1,
+            ")" | "}" | "]" => depth = depth.saturating_sub(1),
+            _ => {}
+        }
+
+        prev_line = token.end_line();
+        // the name of an `@attribute` still needs the function after it
+        if prev != "@" || !token.is_name() {
+            prev = token.text;
+        }
+    }
+    statements
+}
+
+/// Adds a hit counter to the start of every line that starts a statement.
+/// Counters are inserted on the same line, so line numbers do not change
+/// and errors in instrumented modules still point at the right place.
+pub fn instrument(source: &str, name: &str) -> Instrumented {
+    let statements = statement_lines(source);
+    let mut out = String::with_capacity(source.len() + statements.len() * 20);
+    let mut last = 0;
+
+    for (index, &(line, offset)) in statements.iter().enumerate() {
+        out.push_str(&source[last..offset]);
+        if index == 0 {
+            let line_count = source.lines().count();
+            write!(out, "local __cov = {COUNTERS}({name:?}, {line_count}); ").unwrap();
+        }
+        write!(out, "__cov[{line}] += 1; ").unwrap();
+        last = offset;
+    }
+    out.push_str(&source[last..]);
+
+    Instrumented {
+        source: out,
+        lines: statements.into_iter().map(|(line, _)| line).collect(),
+    }
+}
+
+/// Instruments every script in `dom` that was read from a file in
+/// `sources`, returning the counted lines of each script by full name.
+pub fn instrument_dom(dom: &mut WeakDom, sources: &SourceMap) -> BTreeMap<String, Vec<u32>> {
+    let source_key = ustr("Source");
+    let mut lines = BTreeMap::new();
+    let mut stack = vec![(dom.root_ref(), String::new())];
+
+    while let Some((referent, parent_name)) = stack.pop() {
+        let inst = dom
+            .get_by_ref_mut(referent)
+            .expect("Instance does not exist");
+        let full_name = if parent_name.is_empty() {
+            inst.name.clone()
+        } else {
+            format!("{parent_name}.{}", inst.name)
+        };
+
+        if sources.contains_key(&full_name)
+            && let Some(Variant::String(source)) = inst.properties.get_mut(&source_key)
+        {
+            let instrumented = instrument(source, &full_name);
+            *source = instrumented.source;
+            lines.insert(full_name.clone(), instrumented.lines);
+        }
+
+        stack.extend(
+            inst.children()
+                .iter()
+                .map(|&child| (child, full_name.clone())),
+        );
+    }
+
+    lines
+}
+
+/// Hit counts by script full name, indexed by line - 1.
+pub type Hits = BTreeMap<String, Vec<u64>>;
+
+/// Decodes the JSON counters a task returns as its binary output. An empty
+/// table is encoded as `[]`.
+pub fn decode(output: &[u8]) -> Result<Hits, String> {
+    match output.trim_ascii() {
+        b"" | b"[]" => Ok(Hits::new()),
+        output => serde_json::from_slice(output).map_err(|e| e.to_string()),
+    }
+}
+
+/// Adds the counters of another shard to `total`.
+pub fn merge(total: &mut Hits, hits: Hits) {
+    for (name, counts) in hits {
+        let total = total.entry(name).or_default();
+        if total.len() < counts.len() {
+            total.resize(counts.len(), 0);
+        }
+        for (total, count) in total.iter_mut().zip(counts) {
+            *total += count;
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct FileCoverage {
+    pub path: PathBuf,
+    /// Hits by original line number.
+    pub lines: BTreeMap<u32, u64>,
+}
+
+/// Maps the counted lines of each script back to its file, through the
+/// minifier's line maps when the build was minified.
+pub fn report(
+    lines: &BTreeMap<String, Vec<u32>>,
+    hits: &Hits,
+    sources: &SourceMap,
+    line_maps: &BTreeMap<String, Vec<u32>>,
+) -> Vec<FileCoverage> {
+    let mut files: Vec<_> = lines
+        .iter()
+        .filter_map(|(name, lines)| {
+            let path = sources.get(name)?.clone();
+            let counts = hits.get(name).map_or(&[][..], Vec::as_slice);
+            let line_map = line_maps.get(name);
+
+            let mut file_lines = BTreeMap::new();
+            for &line in lines {
+                let index = line as usize - 1;
+                let original = line_map.and_then(|map| map.get(index)).copied();
+                *file_lines.entry(original.unwrap_or(line)).or_default() +=
+                    counts.get(index).copied().unwrap_or(0);
+            }
+            Some(FileCoverage {
+                path,
+                lines: file_lines,
+            })
+        })
+        .collect();
+    files.sort_unstable_by(|a, b| a.path.cmp(&b.path));
+    files
+}
+
+/// Formats a report as an lcov tracefile.
+pub fn to_lcov(files: &[FileCoverage]) -> String {
+    let mut out = String::from("TN:\n");
+    for file in files {
+        writeln!(out, "SF:{}", file.path.display()).unwrap();
+        for (line, hits) in &file.lines {
+            writeln!(out, "DA:{line},{hits}").unwrap();
+        }
+        let hit = file.lines.values().filter(|&&hits| hits > 0).count();
+        writeln!(out, "LF:{}\nLH:{hit}\nend_of_record", file.lines.len()).unwrap();
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn counts_statements_only() {
+        let src = "--!strict\nlocal t = {\n\ta = 1,\n}\nlocal s = \"a\"\n\t.. \"b\"\nif t.a then\n\tprint(s)\nend\nreturn t";
+        let instrumented = instrument(src, "M");
+        assert_eq!(instrumented.lines, [2, 5, 7, 8, 10]);
+        assert_eq!(
+            instrumented.source.lines().collect::<Vec<_>>(),
+            [
+                "--!strict",
+                "local __cov = shared.__SandboxerCoverage(\"M\", 10); __cov[2] += 1; local t = {",
+                "\ta = 1,",
+                "}",
+                "__cov[5] += 1; local s = \"a\"",
+                "\t.. \"b\"",
+                "__cov[7] += 1; if t.a then",
+                "\t__cov[8] += 1; print(s)",
+                "end",
+                "__cov[10] += 1; return t",
+            ]
+        );
+    }
+
+    #[test]
+    fn skips_continued_expressions() {
+        let src = "local x =\n\tfoo\nlocal y = a and\n\tb\nlocal z = [[\nnot code\n]]\nlocal f = g(\n\th\n)";
+        assert_eq!(instrument(src, "M").lines, [1, 3, 5, 8]);
+
+        let src = "return\n\tfoo\n@native\nfunction bar()\nend";
+        assert_eq!(instrument(src, "M").lines, [1, 3]);
+    }
+
+    #[test]
+    fn ignores_comments_and_strings() {
+        let src = "--[=[\n\tlocal a\n]=]\nlocal s = `{x}\nlocal b`\n-- local c\nlocal d = 1";
+        assert_eq!(instrument(src, "M").lines, [4, 7]);
+    }
+
+    #[test]
+    fn instruments_repository_sources() {
+        for path in ["../src/init.luau", "../src/InstanceSandboxer.luau"] {
+            let src = std::fs::read_to_string(path).unwrap();
+            let instrumented = instrument(&src, "Sandboxer");
+            assert!(instrumented.lines.len() > 10);
+            assert_eq!(
+                instrumented.source.lines().count(),
+                src.lines().count(),
+                "{path}"
+            );
+            // directives must stay at the top
+            assert!(instrumented.source.starts_with("--!strict\n"));
+        }
+    }
+
+    #[test]
+    fn merges_and_reports_hits() {
+        let mut hits = decode(br#"{"Sandboxer": [0, 2, 0]}"#).unwrap();
+        merge(
+            &mut hits,
+            decode(br#"{"Sandboxer": [0, 1, 0, 0]}"#).unwrap(),
+        );
+        merge(&mut hits, decode(b"[]").unwrap());
+        assert_eq!(hits["Sandboxer"], [0, 3, 0, 0]);
+
+        let lines = BTreeMap::from([("Sandboxer".to_owned(), vec![2, 4])]);
+        let sources = SourceMap::from([("Sandboxer".to_owned(), PathBuf::from("src/init.luau"))]);
+        let line_maps = BTreeMap::from([("Sandboxer".to_owned(), vec![1, 5, 6, 9])]);
+        let files = report(&lines, &hits, &sources, &line_maps);
+        assert_eq!(
+            to_lcov(&files),
+            "TN:\nSF:src/init.luau\nDA:5,3\nDA:9,0\nLF:2\nLH:1\nend_of_record\n"
+        );
+    }
+}
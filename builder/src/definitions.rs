@@ -0,0 +1,344 @@
+use std::{collections::BTreeMap, fmt::Write, fs, sync::LazyLock};
+
+use regex::{Captures, Regex};
+
+use crate::{
+    api::{Member, members},
+    lexer::{Token, tokenize},
+    project::SourceMap,
+};
+
+pub const DEFINITIONS_FILE: &str = "Sandboxer.d.luau";
+
+/// Modules that get a type in the definitions, in the order they are
+/// written. Each type is named after its module table.
+const MODULES: [&str; 4] = ["Config", "InstanceList", "InstanceSandboxer", "Sandboxer"];
+
+/// `Module.Type` and `typeof(Module)`, which only resolve inside the modules.
+static MODULE_REFERENCE: LazyLock<Regex> = LazyLock::new(|| {
+    let modules = MODULES.join("|");
+    Regex::new(&format!(
+        r"\btypeof\(({modules})\)|\b({modules})\.([A-Za-z_]\w*)"
+    ))
+    .unwrap()
+});
+
+/// Operators that continue a type on the next line.
+const CONTINUATIONS: [&str; 5] = ["|", "&", "->", ",", "="];
+
+/// The type of a member before aliases of other modules are resolved.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Field {
+    Type(String),
+    /// `Module.member = Other.member`
+    Alias(String, String),
+}
+
+fn at_line_start(source: &str, token: &Token) -> bool {
+    token.offset == 0 || source.as_bytes()[token.offset - 1] == b'\n'
+}
+
+/// Top-level `type` and `export type` declarations by name, without
+/// `export` since definitions declare global types.
+pub fn type_declarations(source: &str) -> Vec<(String, String)> {
+    let tokens = tokenize(source);
+    let mut declarations = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        if !at_line_start(source, token) {
+            continue;
+        }
+        let start = match (token.text, tokens.get(index + 1).map(|t| t.text)) {
+            ("export", Some("type")) => index + 1,
+            ("type", Some(next)) if next != "(" => index,
+            _ => continue,
+        };
+        let Some(name) = tokens.get(start + 1).filter(|t| t.is_name()) else {
+            continue;
+        };
+        let Some(equals) = tokens[start..].iter().position(|t| t.text == "=") else {
+            continue;
+        };
+
+        // a type ends at the first line that does not continue it
+        let mut depth = 0i32;
+        let mut end = start + equals;
+        for (i, token) in tokens.iter().enumerate().skip(end + 1) {
+            let continues =
+                CONTINUATIONS.contains(&tokens[i - 1].text) || CONTINUATIONS.contains(&token.text);
+            if depth == 0 && token.line > tokens[i - 1].end_line() && !continues {
+                break;
+            }
+            match token.text {
+                "(" | "{" | "[" => depth += 1,
+                ")" | "}" | "]" => depth -= 1,
+                _ => {}
+            }
+            end = i;
+        }
+        let last = &tokens[end];
+        declarations.push((
+            name.text.to_owned(),
+            source[tokens[start].offset..last.offset + last.text.len()]
+                .lines()
+                .map(str::trim_end)
+                .collect::<Vec<_>>()
+                .join("\n"),
+        ));
+    }
+    declarations
+}
+
+/// Splits a parameter list at the commas outside of nested types.
+fn split_parameters(parameters: &str) -> Vec<&str> {
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut out = Vec::new();
+    let mut previous = ' ';
+    for (i, c) in parameters.char_indices() {
+        match c {
+            '(' | '{' | '[' | '<' => depth += 1,
+            '>' if previous == '-' => {}
+            ')' | '}' | ']' | '>' => depth -= 1,
+            ',' if depth == 0 => {
+                out.push(parameters[start..i].trim());
+                start = i + 1;
+            }
+            _ => {}
+        }
+        previous = c;
+    }
+    let last = parameters[start..].trim();
+    if !last.is_empty() {
+        out.push(last);
+    }
+    out
+}
+
+/// Converts a signature like `M:name(a: number, b): string` to the type
+/// of the member, `(self: M, a: number, b: any) -> string`.
+fn function_type(signature: &str, module: &str) -> Option<String> {
+    let open = signature.find('(')?;
+    let head = &signature[..open];
+    let generics = head.find('<').map_or("", |i| &head[i..]);
+    let method = head.contains(':');
+
+    let mut depth = 0;
+    let close = open
+        + signature[open..].find(|c| {
+            match c {
+                '(' => depth += 1,
+                ')' => depth -= 1,
+                _ => {}
+            }
+            depth == 0
+        })?;
+
+    let mut parameters: Vec<_> = split_parameters(&signature[open + 1..close])
+        .into_iter()
+        .map(|parameter| match parameter.strip_prefix("...") {
+            Some("") => "...any".to_owned(),
+            Some(ty) => format!("...{}", ty.trim_start_matches(':').trim()),
+            None if parameter.contains(':') => parameter.to_owned(),
+            None => format!("{parameter}: any"),
+        })
+        .collect();
+    if method {
+        parameters.insert(0, format!("self: {module}"));
+    }
+
+    let returns = signature[close + 1..].trim().trim_start_matches(':').trim();
+    Some(format!(
+        "{generics}({}) -> {}",
+        parameters.join(", "),
+        if returns.is_empty() { "()" } else { returns }
+    ))
+}
+
+/// The `Other.member` that `Module.member` is last assigned to, if any.
+fn alias(source: &str, module: &str, member: &str) -> Option<(String, String)> {
+    let prefix = format!("{module}.{member} = ");
+    let (other, other_member) = source
+        .lines()
+        .rev()
+        .find_map(|line| line.strip_prefix(&prefix))?
+        .trim()
+        .split_once('.')?;
+    (MODULES.contains(&other)
+        && other_member
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_'))
+    .then(|| (other.to_owned(), other_member.to_owned()))
+}
+
+fn field(source: &str, module: &str, name: &str, member: &Member) -> Field {
+    if member.signature.contains('(') {
+        return Field::Type(function_type(&member.signature, module).unwrap_or("any".to_owned()));
+    }
+    let prop = member.annotations.iter().find_map(|annotation| {
+        let rest = annotation.strip_prefix("@prop ")?;
+        let (_, ty) = rest.split_once(' ')?;
+        Some(ty.to_owned())
+    });
+    match (prop, alias(source, module, name)) {
+        (Some(ty), _) => Field::Type(ty),
+        (None, Some((other, member))) => Field::Alias(other, member),
+        (None, None) => Field::Type("any".to_owned()),
+    }
+}
+
+/// Generates type definitions for the public modules in `sources`: their
+/// types, and a table type per module with the type of each function and
+/// documented property.
+pub fn generate(sources: &SourceMap) -> String {
+    let mut declarations = BTreeMap::new();
+    let mut modules = BTreeMap::new();
+
+    for (full_name, path) in sources {
+        let module = full_name.rsplit('.').next().unwrap_or(full_name);
+        if !MODULES.contains(&module) {
+            continue;
+        }
+        let source = fs::read_to_string(path)
+            .unwrap_or_else(|e| panic!("Failed to read {}: {e}", path.display()));
+        for (name, declaration) in type_declarations(&source) {
+            declarations.entry(name).or_insert(declaration);
+        }
+        let fields: BTreeMap<_, _> = members(&source, module)
+            .iter()
+            .filter(|(name, _)| !name.starts_with("type "))
+            .map(|(name, member)| (name.clone(), field(&source, module, name, member)))
+            .collect();
+        modules.insert(module.to_owned(), fields);
+    }
+
+    let resolve = |module: &str, name: &str| {
+        let (mut module, mut name) = (module, name);
+        // aliases of aliases, up to a limit in case of cycles
+        for _ in 0..MODULES.len() {
+            match modules.get(module).and_then(|fields| fields.get(name)) {
+                Some(Field::Type(ty)) => return ty.as_str(),
+                Some(Field::Alias(other, member)) => (module, name) = (other, member),
+                None => break,
+            }
+        }
+        "any"
+    };
+    let qualify = |ty: &str| {
+        MODULE_REFERENCE
+            .replace_all(ty, |caps: &Captures| match (caps.get(1), caps.get(3)) {
+                (Some(module), _) => module.as_str().to_owned(),
+                (_, Some(name)) if declarations.contains_key(name.as_str()) => {
+                    name.as_str().to_owned()
+                }
+                _ => caps[0].to_owned(),
+            })
+            .into_owned()
+    };
+
+    let mut out = String::from(
+        "-- Luau type definitions for Sandboxer, generated by sandboxer-builder.\n\
+         -- Load this as a definitions file and annotate the required module,\n\
+         -- e.g. `local Sandboxer: Sandboxer = require(path.to.Sandboxer)`.\n",
+    );
+    if !declarations.is_empty() {
+        out.push('\n');
+    }
+    for declaration in declarations.values() {
+        writeln!(out, "{}", qualify(declaration)).unwrap();
+    }
+    for module in MODULES {
+        let Some(fields) = modules.get(module) else {
+            continue;
+        };
+        writeln!(out, "\ntype {module} = {{").unwrap();
+        for name in fields.keys() {
+            writeln!(out, "\t{name}: {},", qualify(resolve(module, name))).unwrap();
+        }
+        out.push_str("}\n");
+    }
+    out
+}
+
+pub fn write(sources: &SourceMap) {
+    fs::write(DEFINITIONS_FILE, generate(sources))
+        .unwrap_or_else(|e| panic!("Failed to write {DEFINITIONS_FILE}: {e}"));
+    info!("Wrote {DEFINITIONS_FILE}");
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn converts_signatures_to_function_types() {
+        assert_eq!(
+            function_type("M.add(a: number, b): number", "M").unwrap(),
+            "(a: number, b: any) -> number"
+        );
+        assert_eq!(
+            function_type("M:method(fn: (a: T) -> (), ...)", "M").unwrap(),
+            "(self: M, fn: (a: T) -> (), ...any) -> ()"
+        );
+        assert_eq!(
+            function_type("M.map<T>(...: T): ...T", "M").unwrap(),
+            "<T>(...T) -> ...T"
+        );
+
+        let source = "\
+export type Fn = (...any) -> ...any
+type Union = (\"a\"
+| \"b\")
+local x: Fn = nil
+type(x)
+export type Table<T> = {
+\tvalue: T,
+}
+return nil";
+        assert_eq!(
+            type_declarations(source),
+            [
+                ("Fn".to_owned(), "type Fn = (...any) -> ...any".to_owned()),
+                (
+                    "Union".to_owned(),
+                    "type Union = (\"a\"\n| \"b\")".to_owned()
+                ),
+                (
+                    "Table".to_owned(),
+                    "type Table<T> = {\n\tvalue: T,\n}".to_owned()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn describes_the_public_modules() {
+        // tests run from the builder directory
+        let sources = SourceMap::from(["init", "InstanceList", "InstanceSandboxer", "Config"].map(
+            |file| {
+                let name = match file {
+                    "init" => "Sandboxer".to_owned(),
+                    file => format!("Sandboxer.{file}"),
+                };
+                (name, PathBuf::from(format!("../src/{file}.luau")))
+            },
+        ));
+        let definitions = generate(&sources);
+        for expected in [
+            "\ntype AnyFn = (...any) -> ...any\n",
+            "\ntype MetamethodHook = (fn: (Instance, ...any) -> ...any, inst: any, ...any) -> ...any\n",
+            "\tConfig: Config,\n",
+            "\tSandbox: (self: Sandboxer, fnOrLevel: AnyFn | number) -> (),\n",
+            "\thookMetamethod: (inst: Instance, name: Metamethod, hook: MetamethodHook?) -> (),\n",
+            "\tisWrapped: (a: any) -> boolean,\n",
+            "\tAllowParallelLuau: boolean,\n",
+        ] {
+            assert!(
+                definitions.contains(expected),
+                "missing {expected:?} in\n{definitions}"
+            );
+        }
+    }
+}
@@ -0,0 +1,493 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    fs::read_to_string,
+    path::Path,
+    process::exit,
+};
+
+use rbx_reflection::{ClassDescriptor, ClassTag, ReflectionDatabase, Scriptability};
+use serde::Deserialize;
+
+use crate::{
+    args::Args,
+    audit::ApiDump,
+    policy::{self, Policy},
+    project::Project,
+};
+
+/// The reviewed classification of engine services and settings classes
+/// (`instance-list.toml`), the source of the `InstanceList` defaults in
+/// `sandboxer.toml` and `Config.luau`. Every one with a scriptable surface
+/// has to be listed here; anything else that shows up in a newer API dump
+/// is disallowed until reviewed.
+#[derive(Deserialize, Debug, Default)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct Overrides {
+    /// Reviewed services left out of both tables, so they are denied by
+    /// default.
+    #[serde(default)]
+    pub reviewed: BTreeSet<String>,
+    /// Services in `InstanceList.Allow`; values are whether descendants are
+    /// allowed. `game` is the DataModel itself.
+    #[serde(default)]
+    pub allow: BTreeMap<String, bool>,
+    /// Services in `InstanceList.ExplicitDisallow`.
+    #[serde(default)]
+    pub deny: BTreeMap<String, bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Classification {
+    Allow {
+        descendants: bool,
+    },
+    Deny {
+        descendants: bool,
+    },
+    Reviewed,
+    /// Nothing scripts can use; denied by default without review.
+    Internal,
+    /// Not in the overrides file; explicitly disallowed until reviewed.
+    Unreviewed,
+}
+
+impl fmt::Display for Classification {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::Allow { descendants: true } => "allow (with descendants)",
+            Self::Allow { descendants: false } => "allow",
+            Self::Deny { descendants: true } => "deny (not descendants)",
+            Self::Deny { descendants: false } => "deny",
+            Self::Reviewed => "denied by default",
+            Self::Internal => "internal",
+            Self::Unreviewed => "UNREVIEWED",
+        })
+    }
+}
+
+#[derive(Debug)]
+pub struct Service {
+    pub name: String,
+    pub tags: Vec<String>,
+    pub classification: Classification,
+    /// Whether the class is a service rather than a settings class. Settings
+    /// classes are only reported, since `InstanceList` resolves the names in
+    /// its tables with `game:GetService`.
+    pub is_service: bool,
+}
+
+impl Overrides {
+    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let source = read_to_string(path)
+            .unwrap_or_else(|_| panic!("Failed to read overrides file {}", path.display()));
+        toml::from_str(&source)
+            .unwrap_or_else(|e| panic!("Invalid overrides file {}: {e}", path.display()))
+    }
+}
+
+/// Classes whose instances scripts reach through `game:GetService`,
+/// `settings()` or `UserSettings()` rather than by creating them.
+const GLOBAL_TAGS: [ClassTag; 3] = [
+    ClassTag::Service,
+    ClassTag::Settings,
+    ClassTag::UserSettings,
+];
+
+fn is_global(class: &ClassDescriptor) -> bool {
+    GLOBAL_TAGS.iter().any(|tag| class.tags.contains(tag))
+}
+
+/// Whether scripts can use any of the members the class itself declares
+/// (inherited `Instance` members are not counted): properties from the
+/// bundled database, and functions and events from `dump` if there is one.
+fn has_scriptable_surface(class: &ClassDescriptor, dump: Option<&ApiDump>) -> bool {
+    let properties = class
+        .properties
+        .values()
+        .any(|property| !matches!(property.scriptability, Scriptability::None));
+    let members = dump.is_some_and(|dump| {
+        dump.classes
+            .iter()
+            .filter(|c| c.name == class.name)
+            .flat_map(|c| &c.members)
+            .any(|member| member.is_scriptable())
+    });
+    properties || members
+}
+
+/// Classifies every service and settings class in `db`, sorted by name.
+pub fn classify(
+    db: &ReflectionDatabase,
+    dump: Option<&ApiDump>,
+    overrides: &Overrides,
+) -> Result<Vec<Service>, String> {
+    let mut seen = BTreeSet::new();
+    let listed = overrides
+        .reviewed
+        .iter()
+        .chain(overrides.allow.keys())
+        .chain(overrides.deny.keys());
+    for name in listed {
+        if !seen.insert(name) {
+            return Err(format!("'{name}' is listed more than once"));
+        }
+        match db.classes.get(name.as_str()) {
+            _ if name == "game" => {}
+            Some(class) if class.tags.contains(&ClassTag::Service) => {}
+            Some(class) if is_global(class) && overrides.reviewed.contains(name) => {}
+            Some(class) if is_global(class) => {
+                return Err(format!(
+                    "'{name}' is a settings class, which can only be listed in Reviewed"
+                ));
+            }
+            Some(_) => return Err(format!("'{name}' is not a service")),
+            None => return Err(format!("'{name}' is not in the API dump")),
+        }
+    }
+
+    let mut services: Vec<_> = db
+        .classes
+        .values()
+        .filter(|class| is_global(class))
+        .map(|class| {
+            let name = class.name.to_string();
+            let classification = if let Some(&descendants) = overrides.allow.get(&name) {
+                Classification::Allow { descendants }
+            } else if let Some(&descendants) = overrides.deny.get(&name) {
+                Classification::Deny { descendants }
+            } else if overrides.reviewed.contains(&name) {
+                Classification::Reviewed
+            } else if has_scriptable_surface(class, dump) {
+                Classification::Unreviewed
+            } else {
+                Classification::Internal
+            };
+
+            let mut tags: Vec<_> = class.tags.iter().map(|tag| format!("{tag:?}")).collect();
+            tags.sort_unstable();
+            Service {
+                name,
+                tags,
+                classification,
+                is_service: class.tags.contains(&ClassTag::Service),
+            }
+        })
+        .collect();
+    services.sort_unstable_by(|a, b| a.name.cmp(&b.name));
+    Ok(services)
+}
+
+/// Builds the `Allow` and `ExplicitDisallow` tables from a classification.
+/// Only services go into the tables; settings classes are left out.
+pub fn tables(
+    services: &[Service],
+    overrides: &Overrides,
+) -> (BTreeMap<String, bool>, BTreeMap<String, bool>) {
+    let mut allow = BTreeMap::new();
+    let mut deny = BTreeMap::new();
+    // the DataModel is not a service, so it only comes from the overrides
+    if let Some(&descendants) = overrides.allow.get("game") {
+        allow.insert("game".to_owned(), descendants);
+    }
+    if let Some(&descendants) = overrides.deny.get("game") {
+        deny.insert("game".to_owned(), descendants);
+    }
+
+    for service in services {
+        let name = service.name.clone();
+        match service.classification {
+            Classification::Allow { descendants } => allow.insert(name, descendants),
+            Classification::Deny { descendants } => deny.insert(name, descendants),
+            Classification::Unreviewed if service.is_service => deny.insert(name, false),
+            Classification::Unreviewed => None,
+            Classification::Reviewed | Classification::Internal => None,
+        };
+    }
+    (allow, deny)
+}
+
+/// Emits the tables as fields for the `Config.InstanceList` constructor.
+pub fn to_luau(
+    db: &ReflectionDatabase,
+    allow: &BTreeMap<String, bool>,
+    deny: &BTreeMap<String, bool>,
+) -> String {
+    let [a, b, c, d] = db.version;
+    let mut out = format!(
+        "-- Generated by `sandboxer-builder instance-list` from API dump {a}.{b}.{c}.{d}\n"
+    );
+    policy::bool_table(&mut out, "Allow", allow);
+    policy::bool_table(&mut out, "ExplicitDisallow", deny);
+    out
+}
+
+/// Compares a policy with the generated tables, returning a description of
+/// every difference. Class lists are checked against the API dump as well.
+pub fn differences(
+    db: &ReflectionDatabase,
+    policy: &Policy,
+    allow: &BTreeMap<String, bool>,
+    deny: &BTreeMap<String, bool>,
+) -> Vec<String> {
+    let list = &policy.instance_list;
+    let mut out = Vec::new();
+
+    for (table, expected, actual) in [
+        ("Allow", allow, &list.allow),
+        ("ExplicitDisallow", deny, &list.explicit_disallow),
+    ] {
+        for (name, value) in expected {
+            match actual.get(name) {
+                Some(actual) if actual == value => {}
+                Some(actual) => out.push(format!("{table}.{name} is {actual}, expected {value}")),
+                None => out.push(format!("{table}.{name} is missing")),
+            }
+        }
+        for name in actual.keys().filter(|name| !expected.contains_key(*name)) {
+            out.push(format!("{table}.{name} is not in the classification"));
+        }
+    }
+
+    for (table, classes) in [
+        ("DisallowedClasses", &list.disallowed_classes),
+        ("ForbiddenClasses", &list.forbidden_classes),
+    ] {
+        for class in classes {
+            if !db.classes.contains_key(class.as_str()) {
+                out.push(format!("{table}: '{class}' is not in the API dump"));
+            }
+        }
+    }
+    out
+}
+
+/// Compares the defaults in the source of the `Config` module, which apply
+/// without a policy file, with the generated tables.
+pub fn config_differences(
+    config: &str,
+    allow: &BTreeMap<String, bool>,
+    deny: &BTreeMap<String, bool>,
+) -> Vec<String> {
+    [("Allow", allow), ("ExplicitDisallow", deny)]
+        .into_iter()
+        .filter_map(|(table, entries)| {
+            let mut expected = String::new();
+            policy::bool_table(&mut expected, table, entries);
+            (!config.replace("\r\n", "\n").contains(&expected))
+                .then(|| format!("InstanceList.{table} does not match the classification"))
+        })
+        .collect()
+}
+
+/// `sandboxer-builder instance-list`: prints the classification and the
+/// generated Luau tables, or with `--check`, fails if the policy or the
+/// `Config` module is out of date.
+pub fn run(args: &Args) {
+    let db = rbx_reflection_database::get_bundled();
+    let overrides = Overrides::read(&args.overrides);
+    let dump = args.api_dump.as_ref().map(ApiDump::read);
+    if dump.is_none() {
+        info!(
+            "Without --api-dump, only properties count as scriptable; services that only declare functions or events are not reported"
+        );
+    }
+    let services = classify(db, dump.as_ref(), &overrides)
+        .unwrap_or_else(|e| panic!("Invalid overrides file {}: {e}", args.overrides.display()));
+
+    let mut internal = 0;
+    for service in &services {
+        match service.classification {
+            Classification::Internal => internal += 1,
+            Classification::Unreviewed if service.is_service => warn!(
+                "{} is not reviewed; add it to {} (it is explicitly disallowed until then)",
+                service.name,
+                args.overrides.display()
+            ),
+            Classification::Unreviewed => warn!(
+                "Settings class {} is not reviewed; add it to Reviewed in {}",
+                service.name,
+                args.overrides.display()
+            ),
+            classification => info!(
+                "{:<36} {classification:<24} {}",
+                service.name,
+                service.tags.join(", ")
+            ),
+        }
+    }
+    info!(
+        "{} services, {internal} without scriptable members (denied by default)",
+        services.len()
+    );
+
+    let (allow, deny) = tables(&services, &overrides);
+    if !args.check {
+        print!("{}", to_luau(db, &allow, &deny));
+        return;
+    }
+
+    let policy_path = args.policy_path();
+    let project = Project::read(&args.project);
+    let (_, sources) = project.build(args.project.parent().unwrap_or(Path::new(".")));
+    let config_path = &sources[&format!("{}.Config", project.name)];
+    let config = read_to_string(config_path)
+        .unwrap_or_else(|e| panic!("Failed to read {}: {e}", config_path.display()));
+
+    let mut failed = false;
+    for (path, differences) in [
+        (
+            policy_path.as_path(),
+            differences(db, &Policy::read(&policy_path), &allow, &deny),
+        ),
+        (config_path, config_differences(&config, &allow, &deny)),
+    ] {
+        if differences.is_empty() {
+            info!("{} matches the classification", path.display());
+        }
+        for difference in &differences {
+            error!(file:% = path.display(); "{}: {difference}", path.display());
+            failed = true;
+        }
+    }
+    if failed {
+        exit(1);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn db() -> &'static ReflectionDatabase<'static> {
+        rbx_reflection_database::get_bundled()
+    }
+
+    fn parse(source: &str) -> Overrides {
+        toml::from_str(source).unwrap()
+    }
+
+    #[test]
+    fn repository_overrides_review_every_service() {
+        let overrides = Overrides::read("../instance-list.toml");
+        let services = classify(db(), None, &overrides).unwrap();
+        let unreviewed: Vec<_> = services
+            .iter()
+            .filter(|s| s.classification == Classification::Unreviewed)
+            .map(|s| s.name.as_str())
+            .collect();
+        assert_eq!(unreviewed, Vec::<&str>::new());
+    }
+
+    #[test]
+    fn repository_policy_matches_classification() {
+        let overrides = Overrides::read("../instance-list.toml");
+        let services = classify(db(), None, &overrides).unwrap();
+        let (allow, deny) = tables(&services, &overrides);
+        let policy = Policy::read("../sandboxer.toml");
+        assert_eq!(
+            differences(db(), &policy, &allow, &deny),
+            Vec::<String>::new()
+        );
+        let config = read_to_string("../src/Config.luau").unwrap();
+        assert_eq!(
+            config_differences(&config, &allow, &deny),
+            Vec::<String>::new()
+        );
+    }
+
+    #[test]
+    fn methods_need_review() {
+        // ReplicatedStorage declares no properties of its own
+        let dump: ApiDump = serde_json::from_str(
+            r#"{"Classes": [{"Name": "ReplicatedStorage", "Members": [
+                {"MemberType": "Function", "Name": "Internal", "Security": "RobloxScriptSecurity"},
+                {"MemberType": "Function", "Name": "Ping", "Security": "None"}
+            ]}]}"#,
+        )
+        .unwrap();
+        let overrides = parse("[Allow]\ngame = false");
+        let classification = |dump| {
+            classify(db(), dump, &overrides)
+                .unwrap()
+                .into_iter()
+                .find(|s| s.name == "ReplicatedStorage")
+                .unwrap()
+                .classification
+        };
+        assert_eq!(classification(None), Classification::Internal);
+        assert_eq!(classification(Some(&dump)), Classification::Unreviewed);
+    }
+
+    #[test]
+    fn unreviewed_services_are_disallowed() {
+        let overrides = parse("[Allow]\ngame = false");
+        let services = classify(db(), None, &overrides).unwrap();
+        let (allow, deny) = tables(&services, &overrides);
+
+        assert_eq!(allow.len(), 1);
+        assert_eq!(deny.get("Workspace"), Some(&false));
+        // no scriptable properties of its own
+        assert!(!deny.contains_key("ReplicatedStorage"));
+    }
+
+    #[test]
+    fn leaves_settings_classes_out_of_the_tables() {
+        let services = [
+            Service {
+                name: "NewService".to_owned(),
+                tags: vec!["Service".to_owned()],
+                classification: Classification::Unreviewed,
+                is_service: true,
+            },
+            Service {
+                name: "NewSettings".to_owned(),
+                tags: vec!["Settings".to_owned()],
+                classification: Classification::Unreviewed,
+                is_service: false,
+            },
+        ];
+        let (allow, deny) = tables(&services, &Overrides::default());
+        assert!(allow.is_empty());
+        assert_eq!(deny, BTreeMap::from([("NewService".to_owned(), false)]));
+    }
+
+    #[test]
+    fn rejects_unknown_and_duplicate_names() {
+        let err = classify(db(), None, &parse("[Allow]\nWorkspaceService = true")).unwrap_err();
+        assert_eq!(err, "'WorkspaceService' is not in the API dump");
+
+        let err = classify(db(), None, &parse("[Deny]\nPart = false")).unwrap_err();
+        assert_eq!(err, "'Part' is not a service");
+
+        let err = classify(
+            db(),
+            None,
+            &parse("Reviewed = [\"Players\"]\n[Deny]\nPlayers = false"),
+        )
+        .unwrap_err();
+        assert_eq!(err, "'Players' is listed more than once");
+    }
+
+    #[test]
+    fn reports_policy_differences() {
+        let allow = BTreeMap::from([("Workspace".to_owned(), true)]);
+        let deny = BTreeMap::from([("InsertService".to_owned(), false)]);
+        let policy = Policy::parse(
+            "[InstanceList]\nForbiddenClasses = [\"NotAClass\"]\n\
+             [InstanceList.Allow]\nWorkspace = false\nPlayers = true",
+        )
+        .unwrap();
+
+        assert_eq!(
+            differences(db(), &policy, &allow, &deny),
+            [
+                "Allow.Workspace is false, expected true",
+                "Allow.Players is not in the classification",
+                "ExplicitDisallow.InsertService is missing",
+                "ForbiddenClasses: 'NotAClass' is not in the API dump",
+            ]
+        );
+    }
+}
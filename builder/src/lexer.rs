@@ -0,0 +1,120 @@
+use crate::minify::{long_bracket_len, long_bracket_level, quoted_len};
+
+/// Multi-character operators, longest first.
+const OPERATORS: [&str; 17] = [
+    "...", "//=", "..=", "..", "==", "~=", "<=", ">=", "//", "->", "::", "+=", "-=", "*=", "/=",
+    "%=", "^=",
+];
+
+/// A token of Luau source. Strings (including long strings) are single
+/// tokens; comments and whitespace are skipped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    /// Byte offset in the source.
+    pub offset: usize,
+    /// 1-based line the token starts on.
+    pub line: u32,
+}
+
+impl Token<'_> {
+    /// Line the token ends on; only strings span several lines.
+    pub fn end_line(&self) -> u32 {
+        self.line + newlines(self.text.as_bytes())
+    }
+
+    pub fn is_name(&self) -> bool {
+        self.text.bytes().next().is_some_and(is_name_start)
+    }
+}
+
+fn is_name_start(b: u8) -> bool {
+    b.is_ascii_alphabetic() || b == b'_'
+}
+
+fn newlines(bytes: &[u8]) -> u32 {
+    bytes.iter().filter(|&&b| b == b'\n').count() as u32
+}
+
+/// Splits `source` into tokens. This is only as precise as the builder
+/// needs: keywords are names, and numbers are not validated.
+pub fn tokenize(source: &str) -> Vec<Token<'_>> {
+    let src = source.as_bytes();
+    let mut tokens = Vec::new();
+    let (mut pos, mut line) = (0, 1);
+
+    while let Some(&b) = src.get(pos) {
+        let len = match b {
+            b'\n' => {
+                line += 1;
+                pos += 1;
+                continue;
+            }
+            b' ' | b'\t' | b'\r' => {
+                pos += 1;
+                continue;
+            }
+            b'-' if src[pos..].starts_with(b"--") => {
+                let len = match long_bracket_level(src, pos + 2) {
+                    Some(level) => long_bracket_len(src, pos + 2, level) + 2,
+                    None => src[pos..]
+                        .iter()
+                        .position(|&b| b == b'\n')
+                        .unwrap_or(src.len() - pos),
+                };
+                line += newlines(&src[pos..pos + len]);
+                pos += len;
+                continue;
+            }
+            b'[' if let Some(level) = long_bracket_level(src, pos) => {
+                long_bracket_len(src, pos, level)
+            }
+            b'"' | b'\'' | b'`' => quoted_len(src, pos, b),
+            b if is_name_start(b) => src[pos..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || **b == b'_')
+                .count(),
+            // numbers, including `1.5e3` and `0xFF`
+            b if b.is_ascii_digit() => src[pos..]
+                .iter()
+                .take_while(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.'))
+                .count(),
+            _ => OPERATORS
+                .iter()
+                .find(|op| src[pos..].starts_with(op.as_bytes()))
+                .map_or(1, |op| op.len()),
+        };
+        let len = len.min(src.len() - pos);
+        // strings and names are ASCII-delimited; other bytes are never compared
+        let token = Token {
+            text: source.get(pos..pos + len).unwrap_or(""),
+            offset: pos,
+            line,
+        };
+        line = token.end_line();
+        tokens.push(token);
+        pos += len;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_tokens() {
+        let tokens = tokenize("local s = [[a\nb]] -- c\nx ..= `{y}` ~= 1.5");
+        let texts: Vec<_> = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(
+            texts,
+            [
+                "local", "s", "=", "[[a\nb]]", "x", "..=", "`{y}`", "~=", "1.5"
+            ]
+        );
+        assert_eq!(tokens[3].line, 1);
+        assert_eq!(tokens[3].end_line(), 2);
+        assert_eq!(tokens[4].line, 3);
+        assert_eq!(tokens[4].offset, 23);
+    }
+}
@@ -0,0 +1,123 @@
+use rbx_dom_weak::InstanceBuilder;
+
+use crate::{module_script_with_source, read_source};
+
+const SPDX_HEADER: &str = "\
+-- SPDX-License-Identifier: AGPL-3.0-or-later
+-- Sandboxer - a Roblox script sandboxer.
+-- Copyright (C) 2026 littleBitsman
+-- Full license text: https://www.gnu.org/licenses/agpl-3.0.txt
+";
+
+const LICENSE_ERROR: &str = "\
+script:Destroy()
+return error(\"This is a LICENSE file (AGPL v3.0)\")";
+
+/// How the license is embedded in `Sandboxer.rbxm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LicenseMode {
+    /// A `LICENSE` ModuleScript with the full text that errors when required.
+    Module,
+    /// A `LICENSE` ModuleScript with only an SPDX header.
+    Spdx,
+    /// A `LICENSE` StringValue with the full text.
+    StringValue,
+    /// No license instance, for internal builds that are not distributed.
+    Omit,
+}
+
+impl LicenseMode {
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "module" => Self::Module,
+            "spdx" => Self::Spdx,
+            "string-value" | "stringvalue" => Self::StringValue,
+            "omit" | "none" => Self::Omit,
+            _ => panic!(
+                "Invalid license mode '{value}' (expected module, spdx, string-value or omit)"
+            ),
+        }
+    }
+}
+
+fn wrap_license(license: &str) -> String {
+    let mut final_license = String::with_capacity(license.len() + 80);
+    final_license.push_str("--[[\n");
+    final_license.push_str(license);
+    final_license.push_str("\n--]]\n\n");
+    final_license.push_str(LICENSE_ERROR);
+    final_license
+}
+
+fn build_with(mode: LicenseMode, read_license: impl FnOnce() -> String) -> Option<InstanceBuilder> {
+    match mode {
+        LicenseMode::Module => Some(module_script_with_source(
+            "LICENSE",
+            wrap_license(&read_license()),
+        )),
+        LicenseMode::Spdx => Some(module_script_with_source(
+            "LICENSE",
+            format!("{SPDX_HEADER}\n{LICENSE_ERROR}"),
+        )),
+        LicenseMode::StringValue => Some(
+            InstanceBuilder::with_property_capacity("StringValue", 1)
+                .with_name("LICENSE")
+                .with_property("Value", read_license()),
+        ),
+        LicenseMode::Omit => {
+            warn!("License omitted from Sandboxer.rbxm; do not distribute this build");
+            None
+        }
+    }
+}
+
+#[inline(always)]
+pub fn build_license(mode: LicenseMode) -> Option<InstanceBuilder> {
+    build_with(mode, || read_source("LICENSE"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::{WeakDom, types::Variant, ustr};
+
+    fn build(mode: LicenseMode) -> Option<WeakDom> {
+        build_with(mode, || "LICENSE TEXT".to_owned()).map(WeakDom::new)
+    }
+
+    fn property(dom: &WeakDom, name: &str) -> String {
+        let root = dom.get_by_ref(dom.root_ref()).unwrap();
+        match root.properties.get(&ustr(name)) {
+            Some(Variant::String(value)) => value.clone(),
+            other => panic!("unexpected {name}: {other:?}"),
+        }
+    }
+
+    #[test]
+    fn module_embeds_full_text() {
+        let dom = build(LicenseMode::Module).unwrap();
+        let source = property(&dom, "Source");
+        assert!(source.starts_with("--[[\nLICENSE TEXT\n--]]"));
+        assert!(source.ends_with(LICENSE_ERROR));
+    }
+
+    #[test]
+    fn spdx_only_has_header() {
+        let dom = build(LicenseMode::Spdx).unwrap();
+        let source = property(&dom, "Source");
+        assert!(source.starts_with("-- SPDX-License-Identifier: AGPL-3.0-or-later"));
+        assert!(!source.contains("LICENSE TEXT"));
+    }
+
+    #[test]
+    fn string_value_holds_text() {
+        let dom = build(LicenseMode::StringValue).unwrap();
+        assert_eq!(dom.get_by_ref(dom.root_ref()).unwrap().class, "StringValue");
+        assert_eq!(property(&dom, "Value"), "LICENSE TEXT");
+    }
+
+    #[test]
+    fn omit_builds_nothing() {
+        assert!(build(LicenseMode::Omit).is_none());
+    }
+}
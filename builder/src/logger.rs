@@ -0,0 +1,238 @@
+use std::{
+    env::var,
+    fmt,
+    fs::File,
+    io::{self, Write},
+    path::Path,
+    sync::{LazyLock, Mutex, RwLock},
+};
+
+use env_filter::{Builder, Filter};
+use log::{Level, LevelFilter, Log, Metadata, Record, kv::Key};
+use regex::Regex;
+
+use crate::macros::get_iso8601;
+
+/// Target of the `print`ed output of Luau tasks; shown as `OUTPUT`.
+pub const OUTPUT: &str = "luau::output";
+/// Target of the Luau task logs of other levels.
+pub const LUAU: &str = "luau";
+/// Target of the panic hook; shown as `FATAL!`.
+pub const FATAL: &str = "fatal";
+
+static ANSI_ESCAPE: LazyLock<Regex> = LazyLock::new(|| Regex::new("\x1B\\[[0-9;]*m").unwrap());
+
+struct Config {
+    filter: Filter,
+    file: Option<Mutex<File>>,
+    /// Write warnings and errors as GitHub Actions workflow commands so they
+    /// show up as annotations.
+    github: bool,
+}
+
+/// Logs to stderr; stdout is reserved for the output of commands.
+struct Logger {
+    config: RwLock<Config>,
+}
+
+static LOGGER: LazyLock<Logger> = LazyLock::new(|| Logger {
+    config: RwLock::new(Config {
+        filter: filter(0),
+        file: None,
+        github: github_actions(),
+    }),
+});
+
+fn github_actions() -> bool {
+    var("GITHUB_ACTIONS").is_ok_and(|v| v == "true")
+}
+
+/// The level of `RUST_LOG` (`info` if unset), unless `-v` or `-q` were given.
+fn filter(verbosity: i8) -> Filter {
+    let mut builder = Builder::new();
+    builder.filter_level(LevelFilter::Info);
+    if let Ok(directives) = var("RUST_LOG") {
+        builder.parse(&directives);
+    }
+    if verbosity != 0 {
+        builder.filter_level(match verbosity {
+            ..=-2 => LevelFilter::Error,
+            -1 => LevelFilter::Warn,
+            1 => LevelFilter::Debug,
+            _ => LevelFilter::Trace,
+        });
+    }
+    builder.build()
+}
+
+fn label(record: &Record) -> &'static str {
+    match (record.target(), record.level()) {
+        (FATAL, _) => fmt!(RED REVERSE BOLD => "FATAL!"),
+        (OUTPUT, _) => fmt!(WHITE BOLD => "OUTPUT"),
+        (_, Level::Error) => fmt!(RED BOLD => "ERROR "),
+        (_, Level::Warn) => fmt!(YELLOW BOLD => "WARN  "),
+        (_, Level::Info) => fmt!(CYAN BOLD => "INFO  "),
+        (_, Level::Debug) => fmt!(MAGENTA BOLD => "DEBUG "),
+        (_, Level::Trace) => fmt!(BLUE BOLD => "TRACE "),
+    }
+}
+
+fn value(record: &Record, key: &str) -> Option<String> {
+    record
+        .key_values()
+        .get(Key::from_str(key))
+        .map(|value| value.to_string())
+}
+
+/// Escapes workflow command data; properties also escape `:` and `,`.
+fn escape(text: &str, property: bool) -> String {
+    let text = text
+        .replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A");
+    if property {
+        text.replace(':', "%3A").replace(',', "%2C")
+    } else {
+        text
+    }
+}
+
+/// `::error file=src/init.luau,line=12::message`, using the `file` and
+/// `line` values of the record when it has them.
+pub fn annotation(record: &Record) -> Option<String> {
+    let command = match record.level() {
+        Level::Error => "error",
+        Level::Warn => "warning",
+        _ => return None,
+    };
+    let properties: Vec<_> = ["file", "line"]
+        .into_iter()
+        .filter_map(|key| Some(format!("{key}={}", escape(&value(record, key)?, true))))
+        .collect();
+    let message = record.args().to_string();
+    Some(format!(
+        "::{command}{}{}::{}",
+        if properties.is_empty() { "" } else { " " },
+        properties.join(","),
+        escape(&ANSI_ESCAPE.replace_all(&message, ""), false)
+    ))
+}
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        self.config.read().unwrap().filter.enabled(metadata)
+    }
+
+    fn log(&self, record: &Record) {
+        let config = self.config.read().unwrap();
+        if !config.filter.matches(record) {
+            return;
+        }
+        // Luau logs carry the time they were created on Roblox
+        let time = value(record, "time").unwrap_or_else(get_iso8601);
+
+        if let Some(file) = &config.file {
+            let line = format!("[{time} {}] {}", label(record), record.args());
+            let _ = writeln!(
+                file.lock().unwrap(),
+                "{}",
+                ANSI_ESCAPE.replace_all(&line, "")
+            );
+        }
+        let line = match annotation(record).filter(|_| config.github) {
+            Some(annotation) => annotation,
+            None => format!("[{time} {}] {}", label(record), record.args()),
+        };
+        let _ = writeln!(io::stderr().lock(), "{line}");
+    }
+
+    fn flush(&self) {
+        if let Some(file) = &self.config.read().unwrap().file {
+            let _ = file.lock().unwrap().flush();
+        }
+    }
+}
+
+/// Logs a record with key-values that are only known at runtime, like the
+/// time and location of Luau log messages.
+pub fn log_with(target: &str, level: Level, values: &[(&str, &str)], args: fmt::Arguments) {
+    log::logger().log(
+        &Record::builder()
+            .target(target)
+            .level(level)
+            .key_values(&values)
+            .args(args)
+            .build(),
+    );
+}
+
+/// Installs the logger with the `RUST_LOG` level, so that errors parsing
+/// the arguments are still logged.
+pub fn init() {
+    if log::set_logger(&*LOGGER).is_ok() {
+        log::set_max_level(LOGGER.config.read().unwrap().filter.filter());
+    }
+}
+
+/// Applies `-v`/`-q` and `--log-file`.
+pub fn configure(verbosity: i8, file: Option<&Path>) {
+    let file = file.map(|path| {
+        Mutex::new(
+            File::create(path)
+                .unwrap_or_else(|e| panic!("Failed to create log file {}: {e}", path.display())),
+        )
+    });
+    let mut config = LOGGER.config.write().unwrap();
+    *config = Config {
+        filter: filter(verbosity),
+        file,
+        github: github_actions(),
+    };
+    log::set_max_level(config.filter.filter());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn with_record<T>(level: Level, f: impl FnOnce(&Record) -> T) -> T {
+        let kvs = [("file", "src/init.luau"), ("line", "12")];
+        f(&Record::builder()
+            .level(level)
+            .args(format_args!("100%: {}", "done\nnext"))
+            .key_values(&kvs)
+            .build())
+    }
+
+    #[test]
+    fn writes_github_annotations() {
+        assert_eq!(
+            with_record(Level::Error, annotation).as_deref(),
+            Some("::error file=src/init.luau,line=12::100%25: done%0Anext")
+        );
+        assert_eq!(
+            with_record(Level::Warn, annotation).unwrap(),
+            "::warning file=src/init.luau,line=12::100%25: done%0Anext"
+        );
+        assert_eq!(with_record(Level::Info, annotation), None);
+
+        assert_eq!(
+            annotation(
+                &Record::builder()
+                    .level(Level::Warn)
+                    .args(format_args!("a, b: c"))
+                    .build()
+            )
+            .unwrap(),
+            "::warning::a, b: c"
+        );
+    }
+
+    #[test]
+    fn verbosity_overrides_the_default_level() {
+        assert_eq!(filter(-2).filter(), LevelFilter::Error);
+        assert_eq!(filter(-1).filter(), LevelFilter::Warn);
+        assert_eq!(filter(1).filter(), LevelFilter::Debug);
+        assert_eq!(filter(3).filter(), LevelFilter::Trace);
+    }
+}
@@ -0,0 +1,324 @@
+use std::{borrow::Cow, collections::HashMap, path::PathBuf};
+
+use log::Level;
+use regex::{Captures, Regex};
+
+use crate::{
+    json::{LogMessageType, LuauExecutionTaskLogEntry},
+    logger::{self, LUAU, OUTPUT},
+    shard::Shard,
+};
+
+/// Messages Roblox emits in every execution session that are never useful.
+pub const DEFAULT_IGNORE: &[&str] = &["Failed to load sound"];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum LogLevel {
+    Output,
+    Info,
+    Warning,
+    Error,
+}
+
+impl LogLevel {
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "output" => Self::Output,
+            "info" => Self::Info,
+            "warn" | "warning" => Self::Warning,
+            "error" => Self::Error,
+            _ => panic!("Invalid log level '{value}' (expected output, info, warning or error)"),
+        }
+    }
+
+    fn of(message_type: &LogMessageType) -> Option<Self> {
+        match message_type {
+            LogMessageType::Output => Some(Self::Output),
+            LogMessageType::Info => Some(Self::Info),
+            LogMessageType::Warning => Some(Self::Warning),
+            LogMessageType::Error => Some(Self::Error),
+            LogMessageType::Unspecified => None,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Output => "output",
+            Self::Info => "info",
+            Self::Warning => "warning",
+            Self::Error => "error",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat {
+    /// Colored (unless disabled) lines on stderr, like the builder's own output.
+    Pretty,
+    /// Uncolored lines on stderr. Implies `--no-color`.
+    Plain,
+    /// One JSON object per line on stdout.
+    Json,
+}
+
+impl LogFormat {
+    pub fn parse(value: &str) -> Self {
+        match value.to_ascii_lowercase().as_str() {
+            "pretty" => Self::Pretty,
+            "plain" => Self::Plain,
+            "json" => Self::Json,
+            _ => panic!("Invalid log format '{value}' (expected pretty, plain or json)"),
+        }
+    }
+}
+
+#[derive(serde::Serialize)]
+struct JsonLogLine<'a> {
+    time: &'a str,
+    level: &'static str,
+    shard: usize,
+    message: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    line: Option<u32>,
+}
+
+#[derive(Debug)]
+struct ScriptFile {
+    path: String,
+    /// Original line of each line of a minified script.
+    line_map: Option<Vec<u32>>,
+}
+
+/// The files the scripts of a task's model were built from, by full name
+/// (`Sandboxer-Tests.RunTests.tests.Integration.test`), to point the stack
+/// frames in Luau logs at repository files.
+#[derive(Debug, Default)]
+pub struct ScriptPaths {
+    files: HashMap<String, ScriptFile>,
+    /// Matches `Full.Name:123` of any known script.
+    frame: Option<Regex>,
+}
+
+impl ScriptPaths {
+    pub fn new(scripts: impl IntoIterator<Item = (String, PathBuf, Option<Vec<u32>>)>) -> Self {
+        let files: HashMap<_, _> = scripts
+            .into_iter()
+            .map(|(name, path, line_map)| {
+                let path = path.to_string_lossy().trim_start_matches("./").to_owned();
+                (name, ScriptFile { path, line_map })
+            })
+            .collect();
+
+        // longest first, so `Sandboxer` does not match `Sandboxer.InstanceList`
+        let mut names: Vec<_> = files.keys().map(|name| regex::escape(name)).collect();
+        names.sort_unstable_by_key(|name| std::cmp::Reverse(name.len()));
+        let frame = (!names.is_empty()).then(|| {
+            Regex::new(&format!(
+                r"(?:ServerScriptService\.)?({}):(\d+)",
+                names.join("|")
+            ))
+            .expect("Invalid stack frame pattern")
+        });
+        Self { files, frame }
+    }
+
+    fn location(&self, caps: &Captures) -> Option<(&str, u32)> {
+        let file = self.files.get(&caps[1])?;
+        let line: u32 = caps[2].parse().ok()?;
+        let line = file
+            .line_map
+            .as_ref()
+            .and_then(|map| map.get(line.checked_sub(1)? as usize))
+            .copied()
+            .unwrap_or(line);
+        Some((&file.path, line))
+    }
+
+    /// Rewrites every stack frame of a known script in `message` to its file
+    /// and original line, returning the first frame's location too.
+    pub fn rewrite<'a>(&self, message: &'a str) -> (Cow<'a, str>, Option<(&str, u32)>) {
+        let Some(frame) = &self.frame else {
+            return (Cow::Borrowed(message), None);
+        };
+        let first = frame
+            .captures(message)
+            .and_then(|caps| self.location(&caps));
+        let message = frame.replace_all(message, |caps: &Captures| match self.location(caps) {
+            Some((path, line)) => format!("{path}:{line}"),
+            None => caps[0].to_owned(),
+        });
+        (message, first)
+    }
+}
+
+pub struct LogOptions {
+    pub ignore: Vec<Regex>,
+    pub level: LogLevel,
+    pub format: LogFormat,
+}
+
+impl LogOptions {
+    pub fn new<I: IntoIterator<Item = S>, S: AsRef<str>>(
+        ignore: I,
+        level: LogLevel,
+        format: LogFormat,
+    ) -> Self {
+        let ignore = DEFAULT_IGNORE
+            .iter()
+            .map(|pattern| regex::escape(pattern))
+            .chain(ignore.into_iter().map(|p| p.as_ref().to_owned()))
+            .map(|pattern| {
+                Regex::new(&pattern)
+                    .unwrap_or_else(|e| panic!("Invalid log ignore pattern '{pattern}': {e}"))
+            })
+            .collect();
+        Self {
+            ignore,
+            level,
+            format,
+        }
+    }
+
+    /// Returns the level of `entry` if it passes the level and ignore filters.
+    fn accept(&self, entry: &LuauExecutionTaskLogEntry) -> Option<LogLevel> {
+        let level = LogLevel::of(&entry.message_type).filter(|&l| l >= self.level)?;
+        if self.ignore.iter().any(|re| re.is_match(&entry.message)) {
+            return None;
+        }
+        Some(level)
+    }
+
+    pub fn print(&self, entry: &LuauExecutionTaskLogEntry, shard: Shard, scripts: &ScriptPaths) {
+        let Some(level) = self.accept(entry) else {
+            return;
+        };
+        let (message, location) = scripts.rewrite(&entry.message);
+
+        if self.format == LogFormat::Json {
+            let line = JsonLogLine {
+                time: &entry.create_time,
+                level: level.as_str(),
+                shard: shard.index + 1,
+                message: &message,
+                file: location.map(|(file, _)| file),
+                line: location.map(|(_, line)| line),
+            };
+            println!(
+                "{}",
+                serde_json::to_string(&line).expect("Failed to serialize log line")
+            );
+            return;
+        }
+
+        let (target, level) = match level {
+            LogLevel::Error => (LUAU, Level::Error),
+            LogLevel::Warning => (LUAU, Level::Warn),
+            LogLevel::Info => (LUAU, Level::Info),
+            LogLevel::Output => (OUTPUT, Level::Info),
+        };
+        let line = location.map(|(_, line)| line.to_string());
+        let mut values = vec![("time", entry.create_time.as_str())];
+        if let (Some((file, _)), Some(line)) = (location, &line) {
+            values.extend([("file", file), ("line", line.as_str())]);
+        }
+        logger::log_with(target, level, &values, format_args!("{message}"));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(message: &str, message_type: LogMessageType) -> LuauExecutionTaskLogEntry {
+        LuauExecutionTaskLogEntry {
+            message: message.to_owned(),
+            create_time: "2026-01-01T00:00:00.000Z".to_owned(),
+            message_type,
+        }
+    }
+
+    #[test]
+    fn ignores_default_and_custom_patterns() {
+        let options = LogOptions::new([r"^Loading \w+"], LogLevel::Output, LogFormat::Plain);
+        assert_eq!(
+            options.accept(&entry(
+                "Failed to load sound rbxassetid://1",
+                LogMessageType::Warning
+            )),
+            None
+        );
+        assert_eq!(
+            options.accept(&entry(
+                "Loading Integration.test...",
+                LogMessageType::Output
+            )),
+            None
+        );
+        assert_eq!(
+            options.accept(&entry("✓ All tests passed!", LogMessageType::Output)),
+            Some(LogLevel::Output)
+        );
+    }
+
+    #[test]
+    fn filters_by_minimum_level() {
+        let options = LogOptions::new([] as [&str; 0], LogLevel::Warning, LogFormat::Pretty);
+        assert_eq!(options.accept(&entry("hi", LogMessageType::Info)), None);
+        assert_eq!(
+            options.accept(&entry("oh no", LogMessageType::Error)),
+            Some(LogLevel::Error)
+        );
+        assert_eq!(
+            options.accept(&entry("?", LogMessageType::Unspecified)),
+            None
+        );
+    }
+
+    #[test]
+    fn maps_stack_frames_to_files() {
+        let scripts = ScriptPaths::new([
+            (
+                "Sandboxer-Tests.RunTests.tests.Integration.test".to_owned(),
+                PathBuf::from("./builder/src/luau/scripts/Integration.test.luau"),
+                None,
+            ),
+            (
+                "Sandboxer-Tests.Sandboxer".to_owned(),
+                PathBuf::from("src/init.luau"),
+                None,
+            ),
+            (
+                "Sandboxer-Tests.Sandboxer.InstanceSandboxer".to_owned(),
+                PathBuf::from("src/InstanceSandboxer.luau"),
+                Some(vec![1, 4, 9]),
+            ),
+        ]);
+        let (message, location) = scripts.rewrite(
+            "ServerScriptService.Sandboxer-Tests.Sandboxer.InstanceSandboxer:3: oops\n\
+             Sandboxer-Tests.Sandboxer:12 function Sandbox\n\
+             Sandboxer-Tests.RunTests.tests.Integration.test:123\n\
+             Sandboxer-Tests.RunTests.TestFramework:7",
+        );
+        assert_eq!(
+            message,
+            "src/InstanceSandboxer.luau:9: oops\n\
+             src/init.luau:12 function Sandbox\n\
+             builder/src/luau/scripts/Integration.test.luau:123\n\
+             Sandboxer-Tests.RunTests.TestFramework:7"
+        );
+        assert_eq!(location, Some(("src/InstanceSandboxer.luau", 9)));
+
+        let empty = ScriptPaths::default();
+        let (message, location) = empty.rewrite("Sandboxer-Tests.Sandboxer:1");
+        assert_eq!(message, "Sandboxer-Tests.Sandboxer:1");
+        assert_eq!(location, None);
+    }
+
+    #[test]
+    #[should_panic = "Invalid log ignore pattern '('"]
+    fn rejects_invalid_pattern() {
+        LogOptions::new(["("], LogLevel::Output, LogFormat::Pretty);
+    }
+}
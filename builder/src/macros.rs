@@ -1,6 +1,13 @@
 #![expect(unused_macro_rules)]
 
-use std::num::NonZero;
+use std::{
+    env::var_os,
+    num::NonZero,
+    sync::{
+        LazyLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
 
 use time::{
     UtcDateTime,
@@ -17,8 +24,25 @@ const ISO_FORMAT: Iso8601<
     },
 > = Iso8601;
 
+pub fn format_iso8601(time: UtcDateTime) -> String {
+    time.format(&ISO_FORMAT).unwrap()
+}
+
 pub fn get_iso8601() -> String {
-    UtcDateTime::now().format(&ISO_FORMAT).unwrap()
+    format_iso8601(UtcDateTime::now())
+}
+
+/// Colors are on unless `NO_COLOR` is set to a non-empty value
+/// (<https://no-color.org>) or `--no-color` is passed.
+static COLOR: LazyLock<AtomicBool> =
+    LazyLock::new(|| AtomicBool::new(var_os("NO_COLOR").is_none_or(|v| v.is_empty())));
+
+pub fn color_enabled() -> bool {
+    COLOR.load(Ordering::Relaxed)
+}
+
+pub fn set_color(enabled: bool) {
+    COLOR.store(enabled, Ordering::Relaxed);
 }
 
 macro_rules! fmt {
@@ -95,82 +119,18 @@ macro_rules! fmt {
     };
 
     ($($ident:ident$(,)?)+ => $lit:literal) => {
-        concat!(fmt!($($ident),+), $lit, fmt!(RESET))
+        if $crate::macros::color_enabled() {
+            concat!(fmt!($($ident),+), $lit, fmt!(RESET))
+        } else {
+            $lit
+        }
     };
 
     ($($ident:ident$(,)?)+ => $($tt:tt)*) => {
-        format!(concat!(fmt!($($ident),+), "{}", fmt!(RESET)), format_args!($($tt)*))
+        if $crate::macros::color_enabled() {
+            format!(concat!(fmt!($($ident),+), "{}", fmt!(RESET)), format_args!($($tt)*))
+        } else {
+            format!($($tt)*)
+        }
     }
 }
-
-macro_rules! fprint {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(WHITE BOLD => "OUTPUT"), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(WHITE BOLD => "OUTPUT"), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        fprint!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
-
-macro_rules! warn {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(YELLOW BOLD => "WARN  "), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(YELLOW BOLD => "WARN  "), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        warn!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
-
-macro_rules! error {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(RED BOLD => "ERROR "), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(RED BOLD => "ERROR "), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        error!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
-
-macro_rules! fatal {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(RED REVERSE BOLD => "FATAL!"), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(RED REVERSE BOLD => "FATAL!"), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        fatal!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
-
-macro_rules! info {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(CYAN BOLD => "INFO  "), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(CYAN BOLD => "INFO  "), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        info!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
-
-macro_rules! debug {
-    () => {
-        eprintln!(concat!("[{} ", fmt!(MAGENTA BOLD => "DEBUG "), "]"), $crate::macros::get_iso8601())
-    };
-    (time = $expr:expr; $($tt:tt)*) => {
-        eprintln!(concat!("[{} ", fmt!(MAGENTA BOLD => "DEBUG "), "] {}"), $expr, format_args!($($tt)*))
-    };
-    ($($tt:tt)*) => {
-        debug!(time = $crate::macros::get_iso8601(); $($tt)*)
-    };
-}
\ No newline at end of file
@@ -1,23 +1,79 @@
 use std::{
+    collections::BTreeMap,
     env::var as env,
     fs::{self, read_dir, read_to_string},
     panic::{PanicHookInfo, set_hook as set_panic_hook},
+    path::{Path, PathBuf},
     process,
-    thread::sleep,
-    time::Duration,
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
 };
 
-mod json;
-use json::*;
+mod args;
+use args::{Args, Command};
+
+#[macro_use]
+extern crate log;
 
 #[macro_use]
 mod macros;
 
-use rbx_dom_weak::{InstanceBuilder, WeakDom};
+mod api;
+mod audit;
+use audit::ApiDump;
+mod bench;
+
+mod binary_cache;
+use binary_cache::BinaryCache;
+
+mod cancel;
+
+mod coverage;
+
+mod definitions;
+
+mod instance_list;
+
+mod json;
+use json::*;
+
+mod lexer;
+
+mod license;
 
-use reqwest::blocking::Client;
+mod logger;
+mod logs;
+use logs::{LogFormat, LogOptions, ScriptPaths};
+
+mod minify;
+
+mod mutation;
+
+mod output;
+
+mod policy;
+use policy::Policy;
+
+mod project;
+use project::{Project, SourceMap};
+
+mod publish;
+
+mod shard;
+use shard::Shard;
+
+mod version;
+use version::BuildInfo;
+
+mod watch;
+
+use rbx_dom_weak::{InstanceBuilder, WeakDom, types::Attributes};
 
 const SCRIPT: &str = include_str!("main.luau");
+/// The experience the tests run in.
+const UNIVERSE_ID: u64 = 8382727792;
+const PLACE_ID: u64 = 122953816609099;
 
 macro_rules! unwrap {
     (unsafe $expr:expr) => {
@@ -25,7 +81,7 @@ macro_rules! unwrap {
     };
 }
 fn panic_hook(info: &PanicHookInfo) {
-    fatal!("{}", info.payload_as_str().unwrap_or("explicit panic"));
+    error!(target: logger::FATAL, "{}", info.payload_as_str().unwrap_or("explicit panic"));
     if let Some(location) = info.location() {
         debug!("panicked at {location}");
     }
@@ -41,55 +97,125 @@ fn read_source<P: AsRef<str>>(path: P) -> String {
     read_to_string(path.as_ref()).unwrap_or_else(|_| panic!("Failed to read {}", path.as_ref()))
 }
 
-#[inline(always)]
-fn build_license() -> String {
-    let license = read_source("LICENSE");
-    let mut final_license = String::with_capacity(license.len() + 80);
-    final_license.push_str("--[[\n");
-    final_license.push_str(&license);
-    final_license.push_str("\n--]]\n\n");
-    final_license.push_str("script:Destroy()\n");
-    final_license.push_str("return error(\"This is a LICENSE file (AGPL v3.0)\")");
-    final_license
+/// The Sandboxer tree, with what is needed to map its scripts back to files.
+struct SandboxerBuild {
+    dom: WeakDom,
+    /// Script files by full name, without the modules generated from the
+    /// policy.
+    sources: SourceMap,
+    /// Line maps of minified scripts by full name; empty without `--minify`.
+    line_maps: BTreeMap<String, Vec<u32>>,
+    build_info: BuildInfo,
+    /// SHA-256 of the written Sandboxer.rbxm.
+    sha256: String,
 }
 
 #[inline(always)]
-fn build_sandboxer_dom() -> WeakDom {
-    let license = build_license();
-
-    let sandboxer_source = read_source("./src/init.luau");
-    let instancelist_source = read_source("./src/InstanceList.luau");
-    let instancesandboxer_source = read_source("./src/InstanceSandboxer.luau");
-    let config_source = read_source("./src/Config.luau");
-
-    let dom = WeakDom::new(
-        InstanceBuilder::with_property_capacity("ModuleScript", 1)
-            .with_name("Sandboxer")
-            .with_property("Source", sandboxer_source)
-            .with_children([
-                module_script_with_source("InstanceList", instancelist_source),
-                module_script_with_source("InstanceSandboxer", instancesandboxer_source),
-                module_script_with_source("Config", config_source),
-                module_script_with_source("LICENSE", license),
-            ]),
+fn build_sandboxer_dom(args: &Args) -> SandboxerBuild {
+    let project = Project::read(&args.project);
+    let base = args.project.parent().unwrap_or(Path::new("."));
+
+    let build_info = BuildInfo::detect(args.build_version.as_deref(), args.reproducible);
+    info!(
+        "Building Sandboxer {} ({}) at {}",
+        build_info.version, build_info.commit, build_info.build_time
     );
 
-    // guesstimate 32KB
-    let mut out = Vec::with_capacity(32 * 1024);
-    rbx_binary::to_writer(&mut out, &dom, &[dom.root_ref()])
-        .expect("Failed to compile Sandboxer file");
-    fs::write("Sandboxer.rbxm", &out).expect("Failed to write Sandboxer.rbxm");
-    info!("Wrote Sandboxer.rbxm ({} bytes)", out.len());
+    let (builder, mut sources) = project.build(base);
+    let mut dom = WeakDom::new(builder.with_property("Attributes", build_info.attributes()));
+    let root = dom.root_ref();
+
+    // an explicit --policy must exist; the default one is optional
+    let policy_path = args.policy_path();
+    if args.policy.is_some() || policy_path.exists() {
+        let policy = Policy::read(&policy_path);
+        let dump = args.api_dump.as_ref().map(ApiDump::read);
+        policy
+            .check_members(dump.as_ref())
+            .unwrap_or_else(|e| panic!("Invalid policy file {}: {e}", policy_path.display()));
+        policy::apply(&mut dom, &policy);
+        for name in policy::GENERATED_MODULES {
+            sources.remove(&format!("{}.{name}", project.name));
+        }
+        info!(
+            "Generated Config and DeniedMembers from {}",
+            policy_path.display()
+        );
+    }
+
+    if let Some(license) = license::build_license(args.license) {
+        dom.insert(root, license);
+    }
+
+    let mut line_maps = BTreeMap::new();
+    if args.minify {
+        line_maps = minify::minify_dom(&mut dom);
+        let json = serde_json::to_string_pretty(&line_maps).expect("Failed to serialize line map");
+        fs::write("Sandboxer.linemap.json", json).expect("Failed to write Sandboxer.linemap.json");
+        info!("Wrote Sandboxer.linemap.json");
+    }
+
+    if args.reproducible {
+        dom = output::reproducible(dom);
+    } else if args.xml {
+        output::sort_children(&mut dom, root);
+    }
+
+    let rbxm = output::write_rbxm(&dom, "Sandboxer.rbxm");
+    let rbxmx = args
+        .xml
+        .then(|| output::write_rbxmx(&dom, "Sandboxer.rbxmx"));
+
+    if args.reproducible {
+        let mut files = vec![("Sandboxer.rbxm", rbxm.as_slice())];
+        if let Some(rbxmx) = &rbxmx {
+            files.push(("Sandboxer.rbxmx", rbxmx.as_slice()));
+        }
+        output::write_checksums("SHA256SUMS", &files);
+        info!("Sandboxer.rbxm SHA-256: {}", output::sha256_hex(&rbxm));
+    }
 
-    dom
+    SandboxerBuild {
+        sha256: output::sha256_hex(&rbxm),
+        dom,
+        sources,
+        line_maps,
+        build_info,
+    }
 }
 
-#[inline(always)]
-fn build_test_rbxm(latest_rbxm: &WeakDom) -> Vec<u8> {
-    let init_source = read_source("./builder/src/luau/init.luau");
-    let testframework_source = read_source("./builder/src/luau/TestFramework.luau");
+/// Writes what editor tooling needs to use `Sandboxer.rbxm` in another
+/// project: Luau type definitions and a sourcemap of the model. Both are
+/// released with the model.
+fn write_editor_files(args: &Args, sandboxer: &SandboxerBuild) {
+    // the modules generated from the policy have the same types as their
+    // files, which the build no longer lists
+    let base = args.project.parent().unwrap_or(Path::new("."));
+    let (_, sources) = Project::read(&args.project).build(base);
+    definitions::write(&sources);
+    output::write_sourcemap(&sandboxer.dom, "Sandboxer.rbxm", "sourcemap.json");
+}
 
-    let tests_iter = read_dir("./builder/src/luau/scripts")
+/// Test selection is passed to `RunTests` as newline-separated attributes,
+/// which `TestFramework` reads before any test module is required.
+fn test_selection_attributes(args: &Args) -> Attributes {
+    let mut attributes = Attributes::new();
+    for (name, values) in [
+        ("Filter", &args.filter),
+        ("Suite", &args.suite),
+        ("Exclude", &args.exclude),
+    ] {
+        if !values.is_empty() {
+            attributes.insert(name.to_owned(), values.join("\n").into());
+        }
+    }
+    attributes
+}
+
+/// Reads every test module in `builder/src/luau/scripts`, sorted by name so
+/// shards are stable between runs.
+fn read_test_modules() -> Vec<(String, String)> {
+    let mut tests: Vec<_> = read_dir("./builder/src/luau/scripts")
         .expect("Failed to read scripts directory")
         .filter_map(|entry| {
             let entry = entry.expect("Failed to read test script");
@@ -97,24 +223,37 @@ fn build_test_rbxm(latest_rbxm: &WeakDom) -> Vec<u8> {
             if path.extension().and_then(|s| s.to_str()) != Some("luau") {
                 return None;
             }
-            Some(module_script_with_source(
+            Some((
                 // SAFETY: we know this is a file
                 unwrap!(unsafe path.file_name())
                     .to_string_lossy()
-                    .replace(".luau", "")
-                    .as_str(),
+                    .replace(".luau", ""),
                 read_to_string(&path)
                     .unwrap_or_else(|_| panic!("Failed to read {}", path.display())),
             ))
-        });
+        })
+        .collect();
+    tests.sort_unstable_by(|(a, _), (b, _)| a.cmp(b));
+    tests
+}
+
+#[inline(always)]
+fn build_test_rbxm(latest_rbxm: &WeakDom, args: &Args, tests: Vec<(String, String)>) -> Vec<u8> {
+    let init_source = read_source("./builder/src/luau/init.luau");
+    let testframework_source = read_source("./builder/src/luau/TestFramework.luau");
+
+    let tests_iter = tests
+        .into_iter()
+        .map(|(name, source)| module_script_with_source(&name, source));
 
     let mut dom = WeakDom::new(
         InstanceBuilder::new("Model")
             .with_name("Sandboxer-Tests")
             .with_child(
-                InstanceBuilder::with_property_capacity("ModuleScript", 1)
+                InstanceBuilder::with_property_capacity("ModuleScript", 2)
                     .with_name("RunTests")
                     .with_property("Source", init_source)
+                    .with_property("Attributes", test_selection_attributes(args))
                     .with_children([
                         module_script_with_source("TestFramework", testframework_source),
                         InstanceBuilder::new("Folder")
@@ -132,191 +271,359 @@ fn build_test_rbxm(latest_rbxm: &WeakDom) -> Vec<u8> {
     // guesstimate 64 KB
     let mut buf = Vec::with_capacity(64 * 1000);
     rbx_binary::to_writer(&mut buf, &dom, &[root]).expect("Failed to compile rbxm file");
+    buf
+}
+
+/// Where the scripts of a task's model named `model` came from: the runner
+/// scripts, named relative to the model, and the Sandboxer scripts.
+fn script_paths(
+    model: &str,
+    build: &SandboxerBuild,
+    runner: impl IntoIterator<Item = (String, String)>,
+) -> ScriptPaths {
+    let runner = runner
+        .into_iter()
+        .map(|(name, path)| (format!("{model}.{name}"), PathBuf::from(path), None));
+    let sandboxer = build.sources.iter().map(|(name, path)| {
+        let line_map = build.line_maps.get(name).cloned();
+        (format!("{model}.{name}"), path.clone(), line_map)
+    });
+    ScriptPaths::new(runner.chain(sandboxer))
+}
 
-    match fs::write("test.rbxm", &buf) {
-        Ok(()) => info!("Wrote test.rbxm ({} bytes)", buf.len()),
+/// The files of the scripts in the model `build_test_rbxm` builds.
+fn test_script_paths(build: &SandboxerBuild, tests: &[(String, String)]) -> ScriptPaths {
+    let runner = [
+        ("RunTests", "./builder/src/luau/init.luau"),
+        (
+            "RunTests.TestFramework",
+            "./builder/src/luau/TestFramework.luau",
+        ),
+    ]
+    .map(|(name, path)| (name.to_owned(), path.to_owned()))
+    .into_iter()
+    .chain(tests.iter().map(|(name, _)| {
+        (
+            format!("RunTests.tests.{name}"),
+            format!("./builder/src/luau/scripts/{name}.luau"),
+        )
+    }));
+    script_paths("Sandboxer-Tests", build, runner)
+}
+
+fn write_test_rbxm(buf: &[u8], shard: Shard) {
+    let file_name = shard.file_name();
+    match fs::write(&file_name, buf) {
+        Ok(()) => info!("Wrote {file_name} ({} bytes)", buf.len()),
         Err(e) => {
-            warn!("Failed to write test.rbxm; artifact will not upload to GitHub");
+            warn!("Failed to write {file_name}; artifact will not upload to GitHub");
             warn!("Error: {e}");
         }
     }
+}
 
-    buf
+/// Open Cloud client for `ROBLOX_API_KEY`, sending requests to `--api-url`.
+fn open_cloud(args: &Args) -> opencloud::Client {
+    let api_key = env("ROBLOX_API_KEY").expect("Missing API key");
+    opencloud::Client::new(api_key).with_base_url(&args.api_url)
 }
 
 #[inline(always)]
-fn upload_binary(cli: &Client, api_key: &str, buf: &[u8]) -> LuauExecutionBinaryInputResponse {
+fn upload_binary(client: &opencloud::Client, buf: &[u8]) -> LuauExecutionBinaryInputResponse {
     info!("Uploading test binary...");
-    let binput = cli
-        .post("https://apis.roblox.com/cloud/v2/universes/8382727792/luau-execution-session-task-binary-inputs")
-        .header("X-Api-Key", api_key)
-        .json(&LuauExecutionBinaryInputRequest { size: buf.len() })
-        .send()
-        .expect("Create binary input request failed")
-        .error_for_status()
-        .unwrap_or_else(|e| {
-            let e = e.without_url();
-            match e.status() {
-                Some(status) => panic!("Create binary input request failed with HTTP {status}"),
-                None => panic!("Create binary input request failed: {e}"),
-            }
-        }).json::<LuauExecutionBinaryInputResponse>()
-        .expect("Failed to parse response");
+    let binput = client
+        .create_binary_input(UNIVERSE_ID, buf.len())
+        .unwrap_or_else(|e| panic!("Create binary input request failed: {e}"));
 
-    cli.put(&binput.upload_url)
-        .body(buf.to_vec())
-        .send()
-        .expect("Failed to upload binary input")
-        .error_for_status()
-        .expect("Upload request failed");
+    client
+        .upload_binary_input(&binput, buf.to_vec())
+        .unwrap_or_else(|e| panic!("Failed to upload binary input: {e}"));
 
     info!("Successfully uploaded test binary");
     binput
 }
 
+/// Fails with the HTTP error if the task is rejected, e.g. because the
+/// binary input no longer exists.
 #[inline(always)]
-fn spawn_task(cli: &Client, api_key: &str, binary_path: String) -> LuauExecutionTaskResponse {
-    cli
-        .post("https://apis.roblox.com/cloud/v2/universes/8382727792/places/122953816609099/luau-execution-session-tasks")
-        .header("X-Api-Key", api_key)
-        .json(&LuauExecutionTaskRequest {
+fn spawn_task(
+    client: &opencloud::Client,
+    binary_path: String,
+    timeout: &str,
+) -> opencloud::Result<LuauExecutionTaskResponse> {
+    client.create_task(
+        UNIVERSE_ID,
+        PLACE_ID,
+        &LuauExecutionTaskRequest {
             script: SCRIPT,
-            timeout: "10s",
+            timeout,
             binary_input: binary_path,
             enable_binary_output: true,
-        })
-        .send()
-        .expect("Luau execution session request failed")
-        .error_for_status()
-        .expect("Error while spawning Luau execution session")
-        .json::<LuauExecutionTaskResponse>()
-        .expect("Failed to parse response")
+        },
+    )
 }
 
-const MAX_POLL_DELAY: Duration = Duration::from_secs(30);
 #[inline(always)]
-fn poll_task_state(cli: &Client, api_key: &str, id: &str) -> LuauExecutionTaskResponse {
-    let state_req = cli
-        .get(format!("https://apis.roblox.com/cloud/v2/{id}"))
-        .header("X-Api-Key", api_key);
-
-    let mut delay = Duration::from_secs(1);
-    loop {
-        let resp = unwrap!(unsafe state_req.try_clone())
-            .send()
-            .expect("Luau execution session state request failed")
-            .error_for_status()
-            .expect("Error while checking Luau execution session state")
-            .json::<LuauExecutionTaskResponse>()
-            .expect("Failed to parse response");
-
-        match resp.state {
-            LuauExecutionTaskState::Complete | LuauExecutionTaskState::Failed => return resp,
-            state => info!(
-                "Current state: {state:?}. Waiting {} seconds before polling again...",
+fn poll_task_state(
+    client: &opencloud::Client,
+    id: &str,
+    shard: Shard,
+    deadline: Option<Instant>,
+) -> LuauExecutionTaskResponse {
+    let resp = client
+        .poll_task(id, deadline, |state, delay| {
+            info!(
+                "[{shard}] Current state: {state:?}. Waiting {} seconds before polling again...",
                 delay.as_secs()
-            ),
-        }
+            )
+        })
+        .unwrap_or_else(|e| panic!("Error while checking Luau execution session state: {e}"));
+    if resp.state.is_terminal() {
+        return resp;
+    }
 
-        sleep(delay);
-        delay = MAX_POLL_DELAY.min(delay * 2);
+    warn!("[{shard}] Deadline exceeded; cancelling {id}");
+    cancel::cancel_task(client, id);
+    // report whatever state the cancellation left the task in
+    client
+        .get_task(id)
+        .unwrap_or_else(|e| panic!("Error while checking Luau execution session state: {e}"))
+}
+
+#[inline(always)]
+fn stream_and_print_logs(
+    client: &opencloud::Client,
+    id: &str,
+    shard: Shard,
+    options: &LogOptions,
+    scripts: &ScriptPaths,
+) {
+    info!("------- Luau Output ({shard}) -------");
+    for page in client.task_logs(id) {
+        let entries = page
+            .unwrap_or_else(|e| panic!("Error while fetching Luau execution session logs: {e}"));
+        for entry in entries {
+            options.print(&entry, shard, scripts);
+        }
     }
+    info!("----- End Luau Output ({shard}) -----");
 }
 
 #[inline(always)]
-fn stream_and_print_logs(cli: &Client, api_key: &str, id: &str) {
-    let mut page_token = String::with_capacity(24);
-
-    info!("------- Luau Output -------");
-    loop {
-        let logs_resp = cli
-            .get(format!("https://apis.roblox.com/cloud/v2/{id}/logs?view=STRUCTURED&nextPageToken={page_token}"))
-            .header("X-Api-Key", api_key)
-            .send()
-            .expect("Luau execution session logs request failed")
-            .error_for_status()
-            .expect("Error while fetching Luau execution session logs")
-            .json::<LuauExecutionTaskLogsResponse>()
-            .expect("Failed to parse Luau execution logs");
-
-        for log in logs_resp.luau_execution_session_task_logs {
-            for entry in log.structured_messages {
-                if entry.message.contains("Failed to load sound")
-                    || entry.message_type == LogMessageType::Unspecified
-                {
-                    continue;
-                }
-                match entry.message_type {
-                    LogMessageType::Error => error!(time = entry.create_time; "{}", entry.message),
-                    LogMessageType::Warning => warn!(time = entry.create_time; "{}", entry.message),
-                    LogMessageType::Info => info!(time = entry.create_time; "{}", entry.message),
-                    LogMessageType::Output => fprint!(time = entry.create_time; "{}", entry.message),
-                    LogMessageType::Unspecified => unreachable!(),
-                }
+fn download_binary_output(client: &opencloud::Client, url: &str) -> Vec<u8> {
+    client
+        .download(url)
+        .unwrap_or_else(|e| panic!("Error while downloading binary output: {e}"))
+}
+
+/// Uploads and runs one shard, returning its task path and final state. An
+/// earlier upload of the same bytes is reused while it is cached.
+fn run_shard(
+    client: &opencloud::Client,
+    buf: &[u8],
+    timeout: &str,
+    shard: Shard,
+    deadline: Option<Instant>,
+    cache: &Mutex<BinaryCache>,
+) -> (String, LuauExecutionTaskResponse) {
+    let key = binary_cache::key(UNIVERSE_ID, PLACE_ID, &output::sha256_hex(buf));
+    let cache = || cache.lock().expect("Binary input cache lock poisoned");
+    let cached = cache().get(&key).map(str::to_owned);
+
+    let task = cached.and_then(|path| {
+        info!("[{shard}] Reusing uploaded test binary {path}");
+        match spawn_task(client, path, timeout) {
+            Ok(task) => Some(task),
+            Err(e) if e.is_not_found() => {
+                warn!("[{shard}] Cached test binary was rejected ({e}); uploading again");
+                cache().remove(&key);
+                None
             }
+            Err(e) => panic!("Error while spawning Luau execution session: {e}"),
         }
+    });
+    let id = task
+        .unwrap_or_else(|| {
+            let path = upload_binary(client, buf).path;
+            cache().insert(key.clone(), path.clone());
+            spawn_task(client, path, timeout)
+                .unwrap_or_else(|e| panic!("Error while spawning Luau execution session: {e}"))
+        })
+        .path;
+    cancel::track(&id);
 
-        if let Some(token) = logs_resp.next_page_token {
-            page_token = token;
-        } else {
-            break;
-        }
-    }
-    info!("----- End Luau Output -----");
+    debug!("Luau execution session for {shard} started with ID: {id}");
+    let result = poll_task_state(client, &id, shard, deadline);
+    cancel::untrack(&id);
+    (id, result)
 }
 
 fn main() {
     set_panic_hook(Box::new(panic_hook));
+    logger::init();
 
-    let buf = build_test_rbxm(&build_sandboxer_dom());
-
-    let api_key = env("ROBLOX_API_KEY").expect("Missing API key");
-
-    let cli = Client::new();
+    let args = Args::parse();
+    logger::configure(args.verbosity, args.log_file.as_deref());
+    if args.no_color || args.log_format == LogFormat::Plain {
+        macros::set_color(false);
+    }
+    match args.command {
+        Command::InstanceList => return instance_list::run(&args),
+        Command::Audit => return audit::run(&args),
+        Command::Mutate => return mutation::run(&args),
+        Command::Watch => return watch::run(&args),
+        Command::Bench => return bench::run(&args),
+        Command::Publish => return publish::run(&args),
+        Command::Test => {}
+    }
 
-    let binput = upload_binary(&cli, &api_key, &buf);
-    let response = spawn_task(&cli, &api_key, binput.path);
+    let log_options = LogOptions::new(&args.log_ignore, args.log_level, args.log_format);
+    if !(args.filter.is_empty() && args.suite.is_empty() && args.exclude.is_empty()) {
+        info!(
+            "Test selection: filter {:?}, suite {:?}, exclude {:?}",
+            args.filter, args.suite, args.exclude
+        );
+    }
 
-    let id = response.path;
+    let mut sandboxer = build_sandboxer_dom(&args);
+    api::check(&sandboxer.sources, &args);
+    write_editor_files(&args, &sandboxer);
+    // Sandboxer.rbxm has already been written, so only the tests see the counters
+    let coverage = args
+        .coverage
+        .then(|| coverage::instrument_dom(&mut sandboxer.dom, &sandboxer.sources));
+    let tests = read_test_modules();
+    let scripts = test_script_paths(&sandboxer, &tests);
+    let shards = shard::split(tests, args.shards);
+    let count = shards.len();
+    let bufs: Vec<_> = shards
+        .into_iter()
+        .enumerate()
+        .map(|(index, tests)| {
+            let buf = build_test_rbxm(&sandboxer.dom, &args, tests);
+            write_test_rbxm(&buf, Shard { index, count });
+            buf
+        })
+        .collect();
+
+    let client = open_cloud(&args);
+    cancel::install_handler(client.clone());
+    let deadline = args.deadline.map(|d| Instant::now() + d);
+    let cache = Mutex::new(BinaryCache::open(args.binary_cache.as_deref()));
+
+    let runs: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = bufs
+            .iter()
+            .enumerate()
+            .map(|(index, buf)| {
+                let (client, timeout, cache) = (&client, &args.timeout, &cache);
+                let shard = Shard { index, count };
+                // don't leave the other shards running once one has given up
+                scope.spawn(move || {
+                    cancel::on_panic(client, || {
+                        run_shard(client, buf, timeout, shard, deadline, cache)
+                    })
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .enumerate()
+            .map(|(index, handle)| {
+                handle.join().unwrap_or_else(|_| {
+                    panic!(
+                        "Luau execution session for {} panicked",
+                        Shard { index, count }
+                    )
+                })
+            })
+            .collect()
+    });
+
+    let mut results = Vec::with_capacity(count);
+    let mut hits = coverage::Hits::new();
+    for (index, (id, result)) in runs.into_iter().enumerate() {
+        let shard = Shard { index, count };
+        stream_and_print_logs(&client, &id, shard, &log_options, &scripts);
+
+        match result.state {
+            LuauExecutionTaskState::Complete => {}
+            LuauExecutionTaskState::Failed => {
+                if let Some(err) = result.error {
+                    panic!("Luau execution session for {shard} failed: {}", err.message);
+                } else {
+                    panic!("Luau execution session for {shard} failed for unknown reason");
+                }
+            }
+            LuauExecutionTaskState::Cancelled => {
+                panic!("Luau execution session for {shard} was cancelled")
+            }
+            // the polling loop only returns early when the deadline is exceeded
+            state => panic!(
+                "Luau execution session for {shard} did not finish before the deadline (state: {state:?})"
+            ),
+        }
 
-    debug!("Luau execution session started with ID: {}", id);
-    let result = poll_task_state(&cli, &api_key, &id);
-    stream_and_print_logs(&cli, &api_key, &id);
+        match result.output {
+            Some(LuauExecutionTaskOutput { results: [result] }) => results.push(result),
+            None => panic!("Luau execution session for {shard} has no output"),
+        }
 
-    if result.state == LuauExecutionTaskState::Failed {
-        if let Some(err) = result.error {
-            panic!("Luau execution session failed: {}", err.message);
-        } else {
-            panic!("Luau execution session failed for unknown reason");
+        if coverage.is_some() {
+            let Some(url) = &result.binary_output_url else {
+                panic!("Luau execution session for {shard} has no binary output")
+            };
+            let output = download_binary_output(&client, url);
+            let shard_hits = coverage::decode(&output)
+                .unwrap_or_else(|e| panic!("Invalid coverage output from {shard}: {e}"));
+            coverage::merge(&mut hits, shard_hits);
         }
-    } else if result.state != LuauExecutionTaskState::Complete {
-        // this is handled by the polling loop
-        unreachable!()
     }
 
-    if let Some(LuauExecutionTaskOutput { results: [result] }) = result.output {
-        let percent = if result.total > 0 {
-            (f64::from(result.passed) / f64::from(result.total)) * 100.0
-        } else {
-            100.0
-        };
+    if let Some(lines) = &coverage {
+        let files = coverage::report(lines, &hits, &sandboxer.sources, &sandboxer.line_maps);
+        fs::write("lcov.info", coverage::to_lcov(&files)).expect("Failed to write lcov.info");
+        let found: usize = files.iter().map(|file| file.lines.len()).sum();
+        let hit: usize = files
+            .iter()
+            .map(|file| file.lines.values().filter(|&&hits| hits > 0).count())
+            .sum();
         info!(
-            "Results ({:.02?}): {} suites, {} tests ({} passed, {} failed) - {}% passed",
-            Duration::from_secs_f64(result.time),
-            fmt!(BOLD => "{}", result.suites),
-            fmt!(BOLD => "{}", result.total),
-            fmt!(GREEN BOLD => "{}", result.passed),
-            fmt!(RED BOLD => "{}", result.failed),
-            match percent {
-                100.0 => fmt!(GREEN BOLD => "{:.02}", percent),
-                p if p >= 75.0 => fmt!(YELLOW BOLD => "{:.02}", percent),
-                a => fmt!(RED BOLD => "{:.02}", a),
+            "Wrote lcov.info: {hit} of {found} lines covered ({:.02}%)",
+            if found > 0 {
+                hit as f64 / found as f64 * 100.0
+            } else {
+                100.0
             }
         );
-        process::exit(i32::from(!result.success))
-    } else {
-        panic!("Luau execution session has no output");
     }
+
+    let result = shard::merge_results(results);
+    publish::record_tested(&args, &sandboxer, &result);
+    print_results(&result);
+    process::exit(i32::from(!result.success))
+}
+
+fn print_results(result: &LuauExecutionTaskResult) {
+    let percent = if result.total > 0 {
+        (f64::from(result.passed) / f64::from(result.total)) * 100.0
+    } else {
+        100.0
+    };
+    info!(
+        "Results ({:.02?}): {} suites, {} tests ({} passed, {} failed, {} skipped) - {}% passed",
+        Duration::from_secs_f64(result.time),
+        fmt!(BOLD => "{}", result.suites),
+        fmt!(BOLD => "{}", result.total),
+        fmt!(GREEN BOLD => "{}", result.passed),
+        fmt!(RED BOLD => "{}", result.failed),
+        fmt!(DIM => "{}", result.skipped),
+        match percent {
+            100.0 => fmt!(GREEN BOLD => "{:.02}", percent),
+            p if p >= 75.0 => fmt!(YELLOW BOLD => "{:.02}", percent),
+            a => fmt!(RED BOLD => "{:.02}", a),
+        }
+    );
 }
 
 #[cfg(test)]
@@ -325,17 +632,21 @@ mod tests {
 
     #[cfg(test)]
     #[test]
-    #[should_panic = "explicit"]
+    #[should_panic = "BRuh"]
     fn br() {
         set_panic_hook(Box::new(panic_hook));
-        fprint!("Hello!");
+        info!(target: logger::OUTPUT, "Hello!");
         warn!("idk");
         error!("oops");
         info!("FYI");
         debug!("debugging");
 
         debug!("Done, {}, stuff after", fmt!(RED BOLD => "Hello!"));
-        fprint!(time = fmt!(RED BOLD UNDERLINE => "Bruh this is DEFINITELY not a time"); "Bruh.");
+        info!(
+            target: logger::OUTPUT,
+            time:% = fmt!(RED BOLD UNDERLINE => "Bruh this is DEFINITELY not a time");
+            "Bruh."
+        );
         // std::panic::set_hook(Box::new(|_| std::process::exit(101)));
         panic!("BRuh");
     }
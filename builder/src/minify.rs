@@ -0,0 +1,334 @@
+use std::collections::BTreeMap;
+
+use rbx_dom_weak::{WeakDom, types::Variant, ustr};
+
+/// Minified source along with the original (1-based) line number of every
+/// output line, so errors in the minified module can be traced back.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Minified {
+    pub source: String,
+    pub line_map: Vec<u32>,
+}
+
+struct Minifier<'a> {
+    src: &'a [u8],
+    pos: usize,
+    out: Vec<u8>,
+    line_map: Vec<u32>,
+
+    /// Current logical output line; may span several physical lines when it
+    /// contains a multi-line string or a kept block comment.
+    line: Vec<u8>,
+    line_origin: u32,
+    /// Original line numbers of the physical lines after the first in `line`.
+    embedded: Vec<u32>,
+
+    orig_line: u32,
+    pending_space: bool,
+}
+
+/// Returns the level of a long bracket (`[[` = 0, `[==[` = 2) starting at
+/// `pos`, if there is one.
+pub fn long_bracket_level(src: &[u8], pos: usize) -> Option<usize> {
+    if src.get(pos) != Some(&b'[') {
+        return None;
+    }
+    let level = src[pos + 1..].iter().take_while(|&&b| b == b'=').count();
+    (src.get(pos + 1 + level) == Some(&b'[')).then_some(level)
+}
+
+/// Length of a long bracket string or comment body starting at `start`,
+/// including both brackets.
+pub fn long_bracket_len(src: &[u8], start: usize, level: usize) -> usize {
+    let close = [b"]".as_slice(), &b"=".repeat(level), b"]"].concat();
+    let body = start + level + 2;
+    src[body..]
+        .windows(close.len())
+        .position(|w| w == close)
+        .map_or(src.len() - start, |i| body - start + i + close.len())
+}
+
+/// Length of a quoted string starting at `start`, including the quotes.
+/// Interpolated strings may contain other strings inside `{}`.
+pub fn quoted_len(src: &[u8], start: usize, quote: u8) -> usize {
+    let mut i = start + 1;
+    let mut depth = 0usize;
+    while let Some(&b) = src.get(i) {
+        match b {
+            b'\\' => i += 1,
+            b'{' if quote == b'`' => depth += 1,
+            b'}' if quote == b'`' && depth > 0 => depth -= 1,
+            b'"' | b'\'' | b'`' if depth > 0 => i += quoted_len(src, i, b) - 1,
+            b if b == quote => return i + 1 - start,
+            // unterminated; let the compiler report it
+            b'\n' if quote != b'`' => return i - start,
+            _ => {}
+        }
+        i += 1;
+    }
+    src.len() - start
+}
+
+impl<'a> Minifier<'a> {
+    fn new(src: &'a str) -> Self {
+        Self {
+            src: src.as_bytes(),
+            pos: 0,
+            out: Vec::with_capacity(src.len()),
+            line_map: Vec::new(),
+            line: Vec::new(),
+            line_origin: 1,
+            embedded: Vec::new(),
+            orig_line: 1,
+            pending_space: false,
+        }
+    }
+
+    fn peek(&self, offset: usize) -> Option<u8> {
+        self.src.get(self.pos + offset).copied()
+    }
+
+    fn starts_with(&self, s: &[u8]) -> bool {
+        self.src[self.pos..].starts_with(s)
+    }
+
+    /// Appends code to the current line, inserting a single space for any
+    /// whitespace skipped since the last token.
+    fn push_code(&mut self, b: u8) {
+        if self.line.is_empty() {
+            self.line_origin = self.orig_line;
+        } else if self.pending_space {
+            self.line.push(b' ');
+        }
+        self.pending_space = false;
+        self.line.push(b);
+    }
+
+    /// Copies `len` bytes verbatim, keeping track of embedded newlines.
+    fn copy_verbatim(&mut self, len: usize) {
+        let end = (self.pos + len).min(self.src.len());
+        if self.pos < end {
+            // delimiters are never newlines
+            self.push_code(self.src[self.pos]);
+            self.pos += 1;
+        }
+        while self.pos < end {
+            let b = self.src[self.pos];
+            self.line.push(b);
+            if b == b'\n' {
+                self.orig_line += 1;
+                self.embedded.push(self.orig_line);
+            }
+            self.pos += 1;
+        }
+    }
+
+    /// Skips `len` bytes, keeping track of newlines.
+    fn skip(&mut self, len: usize) {
+        let end = (self.pos + len).min(self.src.len());
+        let newlines = self.src[self.pos..end].iter().filter(|&&b| b == b'\n');
+        self.orig_line += newlines.count() as u32;
+        self.pos = end;
+        self.pending_space = true;
+    }
+
+    fn finish_line(&mut self) {
+        if !self.line.is_empty() {
+            if !self.out.is_empty() {
+                self.out.push(b'\n');
+            }
+            self.out.append(&mut self.line);
+            self.line_map.push(self.line_origin);
+            self.line_map.append(&mut self.embedded);
+        }
+        self.embedded.clear();
+        self.pending_space = false;
+    }
+
+    fn run(mut self) -> Minified {
+        while let Some(b) = self.peek(0) {
+            match b {
+                b'\n' => {
+                    self.pos += 1;
+                    self.finish_line();
+                    self.orig_line += 1;
+                }
+                b' ' | b'\t' | b'\r' => {
+                    self.pos += 1;
+                    self.pending_space = true;
+                }
+                b'-' if self.starts_with(b"--") => {
+                    let comment_start = self.pos;
+                    self.pos += 2;
+                    match long_bracket_level(self.src, self.pos) {
+                        // `--[[ ]]` comments hold the license headers; keep them
+                        Some(0) => {
+                            let len = long_bracket_len(self.src, self.pos, 0) + 2;
+                            self.pos = comment_start;
+                            self.copy_verbatim(len);
+                        }
+                        // `--[=[ ]=]` doc comments
+                        Some(level) => {
+                            let len = long_bracket_len(self.src, self.pos, level);
+                            self.skip(len);
+                        }
+                        None => {
+                            let len = self.src[self.pos..]
+                                .iter()
+                                .position(|&b| b == b'\n')
+                                .unwrap_or(self.src.len() - self.pos);
+                            if self.peek(0) == Some(b'!') && self.line.is_empty() {
+                                // `--!strict`, `--!optimize` and other directives
+                                let directive = &self.src[self.pos..self.pos + len];
+                                let len = directive.trim_ascii_end().len() + 2;
+                                self.pos = comment_start;
+                                self.copy_verbatim(len);
+                            } else {
+                                self.skip(len);
+                            }
+                        }
+                    }
+                }
+                b'[' => match long_bracket_level(self.src, self.pos) {
+                    Some(level) => {
+                        let len = long_bracket_len(self.src, self.pos, level);
+                        self.copy_verbatim(len);
+                    }
+                    None => {
+                        self.push_code(b);
+                        self.pos += 1;
+                    }
+                },
+                b'"' | b'\'' | b'`' => {
+                    let len = quoted_len(self.src, self.pos, b);
+                    self.copy_verbatim(len);
+                }
+                _ => {
+                    self.push_code(b);
+                    self.pos += 1;
+                }
+            }
+        }
+        self.finish_line();
+
+        Minified {
+            // only whole ASCII delimiters are removed, so this stays UTF-8
+            source: String::from_utf8(self.out).expect("Minified source is not UTF-8"),
+            line_map: self.line_map,
+        }
+    }
+}
+
+/// Strips doc comments (`--[=[ ... ]=]`), line comments and redundant
+/// whitespace. Directives (`--!strict`) and `--[[ ]]` block comments, which
+/// hold the license headers, are kept; so is the line structure, so every
+/// output line maps to exactly one original line.
+pub fn minify(source: &str) -> Minified {
+    Minifier::new(source).run()
+}
+
+/// Minifies every script in `dom` in place, returning the line map of each
+/// script keyed by its full name (`Sandboxer.InstanceList`).
+pub fn minify_dom(dom: &mut WeakDom) -> BTreeMap<String, Vec<u32>> {
+    let source_key = ustr("Source");
+    let mut maps = BTreeMap::new();
+    let mut stack = vec![(dom.root_ref(), String::new())];
+
+    while let Some((referent, parent_name)) = stack.pop() {
+        let inst = dom
+            .get_by_ref_mut(referent)
+            .expect("Instance does not exist");
+        let full_name = if parent_name.is_empty() {
+            inst.name.clone()
+        } else {
+            format!("{parent_name}.{}", inst.name)
+        };
+
+        if matches!(
+            inst.class.as_str(),
+            "ModuleScript" | "Script" | "LocalScript"
+        ) && let Some(Variant::String(source)) = inst.properties.get_mut(&source_key)
+        {
+            let minified = minify(source);
+            *source = minified.source;
+            maps.insert(full_name.clone(), minified.line_map);
+        }
+
+        stack.extend(
+            inst.children()
+                .iter()
+                .map(|&child| (child, full_name.clone())),
+        );
+    }
+
+    maps
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strips_doc_comments_and_whitespace() {
+        let src = "--!strict\n--!optimize 2\n\n--[=[\n\t@class Foo\n]=]\nlocal   Foo = {}  -- trailing\n\n\treturn Foo";
+        let minified = minify(src);
+        assert_eq!(
+            minified.source,
+            "--!strict\n--!optimize 2\nlocal Foo = {}\nreturn Foo"
+        );
+        assert_eq!(minified.line_map, [1, 2, 7, 9]);
+    }
+
+    #[test]
+    fn keeps_license_block_comments() {
+        let src = "--[[\nCopyright (C) 2026\n]]\nreturn nil";
+        let minified = minify(src);
+        assert_eq!(minified.source, src);
+        assert_eq!(minified.line_map, [1, 2, 3, 4]);
+    }
+
+    #[test]
+    fn leaves_strings_untouched() {
+        let src = "local a = \"  -- not a comment  \"\nlocal b = [[\n  keep  --[=[ this ]=]\n]]\nlocal c = `{a  ..  \"}\"} --`";
+        let minified = minify(src);
+        assert_eq!(minified.source, src);
+        assert_eq!(minified.line_map, [1, 2, 3, 4, 5]);
+    }
+
+    #[test]
+    fn doc_comment_between_tokens_keeps_them_apart() {
+        let minified = minify("local--[=[ doc ]=]x = 1");
+        assert_eq!(minified.source, "local x = 1");
+    }
+
+    #[test]
+    fn maps_lines_after_multiline_doc_comment() {
+        let minified = minify("local a = 1\n--[=[\n\n\n]=]\nlocal b = a\nerror(b)");
+        assert_eq!(minified.source, "local a = 1\nlocal b = a\nerror(b)");
+        assert_eq!(minified.line_map, [1, 6, 7]);
+    }
+
+    #[test]
+    fn minifies_repository_sources() {
+        for path in ["../src/init.luau", "../src/InstanceSandboxer.luau"] {
+            let src = std::fs::read_to_string(path).unwrap();
+            let minified = minify(&src);
+            assert!(minified.source.len() < src.len());
+            assert!(
+                minified
+                    .source
+                    .starts_with("--!strict\n--!optimize 2\n--[[")
+            );
+            assert!(!minified.source.contains("--[=["));
+            assert_eq!(minified.line_map.len(), minified.source.lines().count());
+
+            // every kept line is found verbatim (modulo whitespace) in the original
+            let original: Vec<_> = src.lines().collect();
+            for (line, &origin) in minified.source.lines().zip(&minified.line_map) {
+                if let Some(first_token) = line.split_whitespace().next() {
+                    assert!(original[origin as usize - 1].contains(first_token));
+                }
+            }
+        }
+    }
+}
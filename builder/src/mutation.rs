@@ -0,0 +1,435 @@
+use std::{fmt, process::exit, sync::Mutex, thread, time::Instant};
+
+use rbx_dom_weak::{
+    WeakDom,
+    types::{Ref, Variant},
+    ustr,
+};
+
+use crate::{
+    SandboxerBuild,
+    args::Args,
+    binary_cache::BinaryCache,
+    build_sandboxer_dom, build_test_rbxm, cancel,
+    json::{LuauExecutionTaskOutput, LuauExecutionTaskResponse, LuauExecutionTaskState},
+    lexer::tokenize,
+    open_cloud, read_test_modules, run_shard,
+    shard::Shard,
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Operator {
+    /// `if x then` becomes `if not (x) then`.
+    FlipCondition,
+    /// `if x then` becomes `if false then`, as if the check was deleted.
+    DeleteCheck,
+    /// `true` becomes `false` and the other way around.
+    SwapBoolean,
+    /// `==` becomes `~=` and the other way around.
+    NegateComparison,
+}
+
+impl fmt::Display for Operator {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.pad(match self {
+            Self::FlipCondition => "flip condition",
+            Self::DeleteCheck => "delete check",
+            Self::SwapBoolean => "swap boolean",
+            Self::NegateComparison => "negate comparison",
+        })
+    }
+}
+
+/// A single change to a script's source.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mutation {
+    pub operator: Operator,
+    pub line: u32,
+    /// Byte range of the replaced source.
+    pub start: usize,
+    pub end: usize,
+    pub replacement: String,
+}
+
+impl Mutation {
+    pub fn apply(&self, source: &str) -> String {
+        [
+            &source[..self.start],
+            &self.replacement,
+            &source[self.end..],
+        ]
+        .concat()
+    }
+}
+
+/// Finds every mutation of `source`: each `if`/`elseif` condition is
+/// flipped and deleted, and each boolean literal and equality comparison
+/// is swapped.
+pub fn mutations(source: &str) -> Vec<Mutation> {
+    let tokens = tokenize(source);
+    let mut out = Vec::new();
+
+    for (index, token) in tokens.iter().enumerate() {
+        let swapped = match token.text {
+            "true" => Some((Operator::SwapBoolean, "false")),
+            "false" => Some((Operator::SwapBoolean, "true")),
+            "==" => Some((Operator::NegateComparison, "~=")),
+            "~=" => Some((Operator::NegateComparison, "==")),
+            _ => None,
+        };
+        if let Some((operator, replacement)) = swapped {
+            out.push(Mutation {
+                operator,
+                line: token.line,
+                start: token.offset,
+                end: token.offset + token.text.len(),
+                replacement: replacement.to_owned(),
+            });
+            continue;
+        }
+        if !matches!(token.text, "if" | "elseif") {
+            continue;
+        }
+
+        // the condition ends at the first `then` outside of brackets
+        let mut depth = 0usize;
+        let condition = tokens[index + 1..].iter().position(|token| {
+            match token.text {
+                "(" | "{" | "[" => depth += 1,
+                ")" | "}" | "]" => depth = depth.saturating_sub(1),
+                "then" => return depth == 0,
+                _ => {}
+            }
+            false
+        });
+        let Some(len @ 1..) = condition else {
+            continue;
+        };
+        let (first, last) = (&tokens[index + 1], &tokens[index + len]);
+        let (start, end) = (first.offset, last.offset + last.text.len());
+        for (operator, replacement) in [
+            (
+                Operator::FlipCondition,
+                format!("not ({})", &source[start..end]),
+            ),
+            (Operator::DeleteCheck, "false".to_owned()),
+        ] {
+            out.push(Mutation {
+                operator,
+                line: token.line,
+                start,
+                end,
+                replacement,
+            });
+        }
+    }
+    out
+}
+
+/// A mutation of one script in the Sandboxer tree.
+struct Mutant {
+    script: String,
+    referent: Ref,
+    original: String,
+    mutation: Mutation,
+}
+
+impl Mutant {
+    /// The file of the script, or its full name for generated modules, and
+    /// the mutated line in it.
+    fn position(&self, build: &SandboxerBuild) -> (String, u32) {
+        let line = build
+            .line_maps
+            .get(&self.script)
+            .and_then(|map| map.get(self.mutation.line as usize - 1))
+            .copied()
+            .unwrap_or(self.mutation.line);
+        match build.sources.get(&self.script) {
+            Some(path) => (path.display().to_string(), line),
+            None => (self.script.clone(), line),
+        }
+    }
+
+    /// `src/InstanceList.luau:180`
+    fn location(&self, build: &SandboxerBuild) -> String {
+        let (file, line) = self.position(build);
+        format!("{file}:{line}")
+    }
+
+    fn describe(&self) -> String {
+        let replaced = &self.original[self.mutation.start..self.mutation.end];
+        let replaced = replaced.split_whitespace().collect::<Vec<_>>().join(" ");
+        format!(
+            "{}: `{replaced}` -> `{}`",
+            self.mutation.operator, self.mutation.replacement
+        )
+    }
+}
+
+/// Whether `full_name` (`Sandboxer.InstanceList`) is selected by `--module`.
+fn selected(full_name: &str, modules: &[String]) -> bool {
+    modules.is_empty()
+        || modules.iter().any(|module| {
+            full_name == module
+                || full_name
+                    .rsplit_once('.')
+                    .is_some_and(|(_, name)| name == module)
+        })
+}
+
+/// Every script in `dom` selected by `modules`, with its full name and source.
+fn scripts(dom: &WeakDom, modules: &[String]) -> Vec<(String, Ref, String)> {
+    let source_key = ustr("Source");
+    let mut scripts = Vec::new();
+    let mut stack = vec![(dom.root_ref(), String::new())];
+
+    while let Some((referent, parent_name)) = stack.pop() {
+        let inst = dom.get_by_ref(referent).expect("Instance does not exist");
+        let full_name = if parent_name.is_empty() {
+            inst.name.clone()
+        } else {
+            format!("{parent_name}.{}", inst.name)
+        };
+
+        if selected(&full_name, modules)
+            && let Some(Variant::String(source)) = inst.properties.get(&source_key)
+        {
+            scripts.push((full_name.clone(), referent, source.clone()));
+        }
+
+        stack.extend(
+            inst.children()
+                .iter()
+                .map(|&child| (child, full_name.clone())),
+        );
+    }
+
+    scripts.sort_unstable_by(|a, b| a.0.cmp(&b.0));
+    scripts
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Outcome {
+    Killed(String),
+    Survived,
+    /// Cancelled or not finished before `--deadline`.
+    Unfinished,
+}
+
+fn outcome(result: &LuauExecutionTaskResponse) -> Outcome {
+    match result.state {
+        LuauExecutionTaskState::Complete => match &result.output {
+            Some(LuauExecutionTaskOutput { results: [result] }) if result.success => {
+                Outcome::Survived
+            }
+            Some(LuauExecutionTaskOutput { results: [result] }) => {
+                Outcome::Killed(format!("{} failed", result.failed))
+            }
+            None => Outcome::Killed("no output".to_owned()),
+        },
+        // mutants that break the module or loop forever fail the task
+        LuauExecutionTaskState::Failed => Outcome::Killed(match &result.error {
+            Some(err) => err.message.clone(),
+            None => "task failed".to_owned(),
+        }),
+        _ => Outcome::Unfinished,
+    }
+}
+
+fn set_source(dom: &mut WeakDom, referent: Ref, source: String) {
+    dom.get_by_ref_mut(referent)
+        .expect("Script does not exist")
+        .properties
+        .insert(ustr("Source"), Variant::String(source));
+}
+
+/// `sandboxer-builder mutate`: runs the tests once per mutant, printing the
+/// mutants no test caught. Exits with 1 if any survived.
+pub fn run(args: &Args) {
+    let mut build = build_sandboxer_dom(args);
+    let mutants: Vec<_> = scripts(&build.dom, &args.modules)
+        .into_iter()
+        .flat_map(|(script, referent, original)| {
+            mutations(&original)
+                .into_iter()
+                .map(move |mutation| Mutant {
+                    script: script.clone(),
+                    referent,
+                    original: original.clone(),
+                    mutation,
+                })
+                .collect::<Vec<_>>()
+        })
+        .collect();
+    if mutants.is_empty() {
+        panic!("No mutants in {:?}", args.modules);
+    }
+    info!("{} mutants", mutants.len());
+
+    if args.dry_run {
+        for mutant in &mutants {
+            println!("{}: {}", mutant.location(&build), mutant.describe());
+        }
+        return;
+    }
+
+    let tests = read_test_modules();
+    let client = open_cloud(args);
+    cancel::install_handler(client.clone());
+    let deadline = args.deadline.map(|d| Instant::now() + d);
+    let shard = Shard { index: 0, count: 1 };
+    let cache = Mutex::new(BinaryCache::open(args.binary_cache.as_deref()));
+
+    info!("Running the tests without mutations...");
+    let buf = build_test_rbxm(&build.dom, args, tests.clone());
+    let (_, result) = run_shard(&client, &buf, &args.timeout, shard, deadline, &cache);
+    if let outcome @ (Outcome::Killed(_) | Outcome::Unfinished) = outcome(&result) {
+        panic!("The tests do not pass without mutations: {outcome:?}");
+    }
+
+    let mut outcomes = Vec::with_capacity(mutants.len());
+    for batch in mutants.chunks(args.jobs) {
+        let bufs: Vec<_> = batch
+            .iter()
+            .map(|mutant| {
+                let mutated = mutant.mutation.apply(&mutant.original);
+                set_source(&mut build.dom, mutant.referent, mutated);
+                let buf = build_test_rbxm(&build.dom, args, tests.clone());
+                set_source(&mut build.dom, mutant.referent, mutant.original.clone());
+                buf
+            })
+            .collect();
+
+        let results: Vec<_> = thread::scope(|scope| {
+            let handles: Vec<_> = bufs
+                .iter()
+                .map(|buf| {
+                    let (client, timeout, cache) = (&client, &args.timeout, &cache);
+                    scope.spawn(move || {
+                        cancel::on_panic(client, || {
+                            run_shard(client, buf, timeout, shard, deadline, cache)
+                        })
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| {
+                    let (_, result) = handle
+                        .join()
+                        .unwrap_or_else(|_| panic!("Luau execution session for a mutant panicked"));
+                    outcome(&result)
+                })
+                .collect()
+        });
+
+        for (mutant, outcome) in batch.iter().zip(results) {
+            let (file, line) = mutant.position(&build);
+            match &outcome {
+                Outcome::Killed(reason) => info!("{file}:{line}: killed ({reason})"),
+                Outcome::Survived => warn!(
+                    file:% = file, line;
+                    "{file}:{line}: survived: {}",
+                    mutant.describe()
+                ),
+                Outcome::Unfinished => warn!(file:% = file, line; "{file}:{line}: not finished"),
+            }
+            outcomes.push(outcome);
+        }
+    }
+
+    let mut survived = 0;
+    for (mutant, outcome) in mutants.iter().zip(&outcomes) {
+        if *outcome == Outcome::Survived {
+            survived += 1;
+            println!("{}: {}", mutant.location(&build), mutant.describe());
+        }
+    }
+    let unfinished = outcomes
+        .iter()
+        .filter(|outcome| **outcome == Outcome::Unfinished)
+        .count();
+    let killed = outcomes.len() - survived - unfinished;
+    info!(
+        "{} mutants: {killed} killed, {survived} survived, {unfinished} not finished ({:.02}% killed)",
+        outcomes.len(),
+        killed as f64 / outcomes.len() as f64 * 100.0
+    );
+    exit(i32::from(survived > 0));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(source: &str) -> Vec<String> {
+        mutations(source)
+            .iter()
+            .map(|mutation| mutation.apply(source))
+            .collect()
+    }
+
+    #[test]
+    fn mutates_conditions() {
+        assert_eq!(
+            applied("if inst == a or (f(x) and y) then\n\treturn false\nend"),
+            [
+                "if not (inst == a or (f(x) and y)) then\n\treturn false\nend",
+                "if false then\n\treturn false\nend",
+                "if inst ~= a or (f(x) and y) then\n\treturn false\nend",
+                "if inst == a or (f(x) and y) then\n\treturn true\nend",
+            ]
+        );
+    }
+
+    #[test]
+    fn mutates_elseif_and_if_expressions() {
+        let mutations =
+            mutations("local x = if a then 1 else 2\nif b then\nelseif c ~= d then\nend");
+        let operators: Vec<_> = mutations.iter().map(|m| (m.line, m.operator)).collect();
+        assert_eq!(
+            operators,
+            [
+                (1, Operator::FlipCondition),
+                (1, Operator::DeleteCheck),
+                (2, Operator::FlipCondition),
+                (2, Operator::DeleteCheck),
+                (3, Operator::FlipCondition),
+                (3, Operator::DeleteCheck),
+                (3, Operator::NegateComparison),
+            ]
+        );
+    }
+
+    #[test]
+    fn ignores_comments_and_strings() {
+        assert_eq!(
+            mutations("-- if true then\nlocal s = \"false == true\"").len(),
+            0
+        );
+    }
+
+    #[test]
+    fn selects_modules_by_name() {
+        let modules = ["InstanceList".to_owned()];
+        assert!(selected("Sandboxer.InstanceList", &modules));
+        assert!(!selected("Sandboxer.InstanceSandboxer", &modules));
+        assert!(!selected("Sandboxer", &modules));
+        assert!(selected("Sandboxer", &[]));
+    }
+
+    #[test]
+    fn mutates_repository_security_checks() {
+        let source = std::fs::read_to_string("../src/InstanceList.luau").unwrap();
+        let mutants = applied(&source);
+        assert!(
+            mutants.contains(
+                &source.replace("if inst:IsA(class) then", "if not (inst:IsA(class)) then")
+            )
+        );
+        assert!(mutants.contains(&source.replace(
+            "if inst == SandboxRoot or inst:IsDescendantOf(SandboxRoot) then",
+            "if false then"
+        )));
+    }
+}
@@ -0,0 +1,259 @@
+use std::{collections::HashMap, fmt::Write, fs};
+
+use rbx_dom_weak::{
+    InstanceBuilder, WeakDom,
+    types::{Ref, Variant},
+};
+use sha2::{Digest, Sha256};
+
+/// Reorders the children of `parent` (recursively) by name, then class, so
+/// that serializers walking the tree visit instances in a stable order.
+pub fn sort_children(dom: &mut WeakDom, parent: Ref) {
+    let mut children: Vec<_> = dom
+        .get_by_ref(parent)
+        .expect("Instance to sort does not exist")
+        .children()
+        .iter()
+        .map(|&child| {
+            let inst = dom
+                .get_by_ref(child)
+                .expect("Child referent does not exist");
+            (inst.name.clone(), inst.class.to_string(), child)
+        })
+        .collect();
+    children.sort_unstable_by(|(a_name, a_class, _), (b_name, b_class, _)| {
+        (a_name, a_class).cmp(&(b_name, b_class))
+    });
+
+    for (_, _, child) in children {
+        // transferring to the same parent moves the child to the end
+        dom.transfer_within(child, parent);
+        sort_children(dom, child);
+    }
+}
+
+/// Rebuilds `dom` for a reproducible build: children are sorted, referents
+/// are numbered in depth-first order instead of being random, and line
+/// endings in `Source` are normalized so Windows checkouts hash the same.
+pub fn reproducible(mut sorted: WeakDom) -> WeakDom {
+    let root = sorted.root_ref();
+    sort_children(&mut sorted, root);
+
+    let mut referents = HashMap::new();
+    let mut stack = vec![root];
+    while let Some(referent) = stack.pop() {
+        let next = format!("{:032x}", referents.len() + 1);
+        referents.insert(referent, next.parse::<Ref>().expect("Invalid referent"));
+        let inst = sorted
+            .get_by_ref(referent)
+            .expect("Instance does not exist");
+        stack.extend(inst.children().iter().rev());
+    }
+
+    fn rebuild(dom: &WeakDom, referent: Ref, referents: &HashMap<Ref, Ref>) -> InstanceBuilder {
+        let inst = dom.get_by_ref(referent).expect("Instance does not exist");
+        let properties = inst.properties.iter().map(|(key, value)| {
+            let value = match value {
+                Variant::String(source) if key.as_str() == "Source" => {
+                    Variant::String(source.replace("\r\n", "\n"))
+                }
+                Variant::Ref(target) => Variant::Ref(*referents.get(target).unwrap_or(target)),
+                value => value.clone(),
+            };
+            (*key, value)
+        });
+
+        InstanceBuilder::with_property_capacity(inst.class, inst.properties.len())
+            .with_referent(referents[&referent])
+            .with_name(inst.name.as_str())
+            .with_properties(properties)
+            .with_children(
+                inst.children()
+                    .iter()
+                    .map(|&child| rebuild(dom, child, referents)),
+            )
+    }
+
+    WeakDom::new(rebuild(&sorted, root, &referents))
+}
+
+/// Writes `dom` as a binary model, returning the written bytes.
+pub fn write_rbxm(dom: &WeakDom, path: &str) -> Vec<u8> {
+    // guesstimate 32KB
+    let mut out = Vec::with_capacity(32 * 1024);
+    rbx_binary::to_writer(&mut out, dom, &[dom.root_ref()])
+        .unwrap_or_else(|e| panic!("Failed to compile {path}: {e}"));
+    fs::write(path, &out).unwrap_or_else(|_| panic!("Failed to write {path}"));
+    info!("Wrote {path} ({} bytes)", out.len());
+    out
+}
+
+/// Writes `dom` as an XML model, returning the written bytes. rbx_xml
+/// numbers referents in the order it visits instances, so output is stable
+/// as long as children are sorted.
+pub fn write_rbxmx(dom: &WeakDom, path: &str) -> Vec<u8> {
+    let mut out = Vec::with_capacity(64 * 1024);
+    rbx_xml::to_writer_default(&mut out, dom, &[dom.root_ref()])
+        .unwrap_or_else(|e| panic!("Failed to compile {path}: {e}"));
+    fs::write(path, &out).unwrap_or_else(|_| panic!("Failed to write {path}"));
+    info!("Wrote {path} ({} bytes)", out.len());
+    out
+}
+
+pub fn sha256_hex(bytes: &[u8]) -> String {
+    format!("{:x}", Sha256::digest(bytes))
+}
+
+/// Writes a manifest in `sha256sum` format, so a rebuild can be checked
+/// with `sha256sum -c`.
+pub fn write_checksums(path: &str, files: &[(&str, &[u8])]) {
+    let mut manifest = String::with_capacity(files.len() * 96);
+    for (name, bytes) in files {
+        // writing to a String cannot fail
+        let _ = writeln!(manifest, "{}  {name}", sha256_hex(bytes));
+    }
+    fs::write(path, &manifest).unwrap_or_else(|_| panic!("Failed to write {path}"));
+    info!("Wrote {path}");
+}
+
+/// A node of a Rojo sourcemap, which editor tooling like luau-lsp reads to
+/// resolve instance paths and `require`s to files.
+#[derive(serde::Serialize, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourcemapNode {
+    pub name: String,
+    pub class_name: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub file_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<SourcemapNode>,
+}
+
+/// Builds the sourcemap of the model `dom` is written to. The root maps to
+/// the model file, which ships next to the sourcemap; the instances inside
+/// it have no files of their own.
+pub fn sourcemap(dom: &WeakDom, model: &str) -> SourcemapNode {
+    fn node(dom: &WeakDom, referent: Ref) -> SourcemapNode {
+        let inst = dom.get_by_ref(referent).expect("Instance does not exist");
+        SourcemapNode {
+            name: inst.name.clone(),
+            class_name: inst.class.to_string(),
+            file_paths: Vec::new(),
+            children: inst
+                .children()
+                .iter()
+                .map(|&child| node(dom, child))
+                .collect(),
+        }
+    }
+
+    let mut root = node(dom, dom.root_ref());
+    root.file_paths.push(model.to_owned());
+    root
+}
+
+pub fn write_sourcemap(dom: &WeakDom, model: &str, path: &str) {
+    let json = serde_json::to_string(&sourcemap(dom, model))
+        .unwrap_or_else(|e| panic!("Failed to serialize {path}: {e}"));
+    fs::write(path, json).unwrap_or_else(|_| panic!("Failed to write {path}"));
+    info!("Wrote {path}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rbx_dom_weak::InstanceBuilder;
+
+    fn names(dom: &WeakDom, parent: Ref) -> Vec<&str> {
+        dom.get_by_ref(parent)
+            .unwrap()
+            .children()
+            .iter()
+            .map(|&r| dom.get_by_ref(r).unwrap().name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn sorts_children_recursively() {
+        let mut dom = WeakDom::new(
+            InstanceBuilder::new("Folder").with_children([
+                InstanceBuilder::new("Folder")
+                    .with_name("b")
+                    .with_children([
+                        InstanceBuilder::new("Folder").with_name("z"),
+                        InstanceBuilder::new("Folder").with_name("y"),
+                    ]),
+                InstanceBuilder::new("ModuleScript").with_name("a"),
+            ]),
+        );
+        let root = dom.root_ref();
+        sort_children(&mut dom, root);
+
+        assert_eq!(names(&dom, root), ["a", "b"]);
+        let b = dom.get_by_ref(root).unwrap().children()[1];
+        assert_eq!(names(&dom, b), ["y", "z"]);
+    }
+
+    #[test]
+    fn reproducible_builds_are_byte_identical() {
+        let build = |order: [&str; 2]| {
+            let dom = WeakDom::new(
+                InstanceBuilder::new("ModuleScript").with_children(order.map(|name| {
+                    InstanceBuilder::new("ModuleScript")
+                        .with_name(name)
+                        .with_property("Source", "return nil\r\n")
+                })),
+            );
+            let dom = reproducible(dom);
+            let mut out = Vec::new();
+            rbx_binary::to_writer(&mut out, &dom, &[dom.root_ref()]).unwrap();
+            out
+        };
+        assert_eq!(
+            sha256_hex(&build(["a", "b"])),
+            sha256_hex(&build(["b", "a"]))
+        );
+    }
+
+    #[test]
+    fn reproducible_referents_are_sequential() {
+        let dom = reproducible(WeakDom::new(
+            InstanceBuilder::new("Folder").with_child(InstanceBuilder::new("Folder")),
+        ));
+        let root = dom.get_by_ref(dom.root_ref()).unwrap();
+        assert_eq!(root.referent().to_string(), format!("{:032x}", 1));
+        assert_eq!(root.children()[0].to_string(), format!("{:032x}", 2));
+    }
+
+    #[test]
+    fn xml_output_is_stable() {
+        let build = || {
+            let mut dom = WeakDom::new(InstanceBuilder::new("Folder").with_children([
+                InstanceBuilder::new("ModuleScript").with_name("b"),
+                InstanceBuilder::new("ModuleScript").with_name("a"),
+            ]));
+            let root = dom.root_ref();
+            sort_children(&mut dom, root);
+            let mut out = Vec::new();
+            rbx_xml::to_writer_default(&mut out, &dom, &[root]).unwrap();
+            out
+        };
+        assert_eq!(build(), build());
+    }
+
+    #[test]
+    fn maps_model_to_its_file() {
+        let dom = WeakDom::new(
+            InstanceBuilder::new("ModuleScript")
+                .with_name("Sandboxer")
+                .with_children([
+                    InstanceBuilder::new("ModuleScript").with_name("InstanceList"),
+                    InstanceBuilder::new("ModuleScript").with_name("Config"),
+                ]),
+        );
+        assert_eq!(
+            serde_json::to_string(&sourcemap(&dom, "Sandboxer.rbxm")).unwrap(),
+            r#"{"name":"Sandboxer","className":"ModuleScript","filePaths":["Sandboxer.rbxm"],"children":[{"name":"InstanceList","className":"ModuleScript"},{"name":"Config","className":"ModuleScript"}]}"#
+        );
+    }
+}
@@ -0,0 +1,556 @@
+use std::{collections::BTreeMap, fmt::Write, fs::read_to_string, path::Path};
+
+use rbx_dom_weak::{WeakDom, types::Variant, ustr};
+use rbx_reflection::{ClassTag, ReflectionDatabase, Scriptability};
+use serde::Deserialize;
+
+use crate::audit::ApiDump;
+
+/// A sandbox policy (`sandboxer.toml`), compiled into the `Config` module.
+/// Unknown keys are rejected so that typos do not silently fall back to the
+/// defaults.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct Policy {
+    #[serde(default)]
+    pub allow_parallel_luau: bool,
+    #[serde(default)]
+    pub track_instances: bool,
+    #[serde(default, rename = "TrackRBXScriptConnections")]
+    pub track_rbx_script_connections: bool,
+    #[serde(default)]
+    pub instance_list: InstanceListPolicy,
+    #[serde(default)]
+    pub globals: BTreeMap<String, GlobalValue>,
+    /// Service members replaced by a function that raises the error message,
+    /// e.g. `HttpService.GetAsync`.
+    #[serde(default)]
+    pub denied_members: BTreeMap<String, BTreeMap<String, String>>,
+}
+
+/// Initial contents of `InstanceList`. Services are named by class, with
+/// `game` standing for the DataModel; values are whether descendants are
+/// allowed.
+#[derive(Deserialize, Debug, Default, PartialEq)]
+#[serde(deny_unknown_fields, rename_all = "PascalCase")]
+pub struct InstanceListPolicy {
+    #[serde(default)]
+    pub allow: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub explicit_disallow: BTreeMap<String, bool>,
+    #[serde(default)]
+    pub disallowed_classes: Vec<String>,
+    #[serde(default)]
+    pub forbidden_classes: Vec<String>,
+}
+
+/// A default sandbox global. Only values that can be written as Luau
+/// literals are supported; `false` removes the global.
+#[derive(Deserialize, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum GlobalValue {
+    Bool(bool),
+    Integer(i64),
+    Float(f64),
+    String(String),
+}
+
+/// Whether `class` or one of its superclasses has a scriptable property
+/// named `member` in the bundled database.
+fn is_property(db: &ReflectionDatabase, class: &str, member: &str) -> bool {
+    let mut next = Some(class);
+    while let Some(name) = next {
+        let Some(descriptor) = db.classes.get(name) else {
+            break;
+        };
+        let property = descriptor
+            .properties
+            .get(member)
+            .is_some_and(|p| !matches!(p.scriptability, Scriptability::None));
+        if property {
+            return true;
+        }
+        next = descriptor.superclass.as_deref();
+    }
+    false
+}
+
+/// Whether `class` or one of its superclasses has a scriptable member named
+/// `member` in `dump`.
+fn is_dump_member(dump: &ApiDump, class: &str, member: &str) -> bool {
+    let mut next = Some(class);
+    while let Some(name) = next {
+        let Some(descriptor) = dump.classes.iter().find(|c| c.name == name) else {
+            break;
+        };
+        if descriptor
+            .members
+            .iter()
+            .any(|m| m.name == member && m.is_scriptable())
+        {
+            return true;
+        }
+        next = Some(descriptor.superclass.as_str());
+    }
+    false
+}
+
+const LUAU_KEYWORDS: [&str; 22] = [
+    "and", "break", "continue", "do", "else", "elseif", "end", "export", "false", "for",
+    "function", "if", "in", "local", "nil", "not", "or", "repeat", "return", "then", "true",
+    "until",
+];
+
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Class names are PascalCase identifiers, e.g. `DataStoreService`.
+fn is_class_name(name: &str) -> bool {
+    name.starts_with(|c: char| c.is_ascii_uppercase()) && is_identifier(name)
+}
+
+fn luau_string(value: &str) -> String {
+    // Rust's escapes (`\n`, `\"`, `\u{..}`) are all valid in Luau strings
+    format!("{value:?}")
+}
+
+fn luau_key(key: &str) -> String {
+    if is_identifier(key) && !LUAU_KEYWORDS.contains(&key) {
+        key.to_owned()
+    } else {
+        format!("[{}]", luau_string(key))
+    }
+}
+
+/// Writes a frozen `{ [string]: boolean }` table as a field of a table
+/// constructor, as used for `InstanceList.Allow` and `ExplicitDisallow`.
+pub fn bool_table(out: &mut String, name: &str, entries: &BTreeMap<String, bool>) {
+    let _ = writeln!(out, "\t{name} = table.freeze({{");
+    for (key, value) in entries {
+        let _ = writeln!(out, "\t\t{} = {value},", luau_key(key));
+    }
+    out.push_str("\t}),\n");
+}
+
+impl GlobalValue {
+    fn to_luau(&self) -> String {
+        match self {
+            Self::Bool(b) => b.to_string(),
+            Self::Integer(i) => i.to_string(),
+            Self::Float(f) => format!("{f:?}"),
+            Self::String(s) => luau_string(s),
+        }
+    }
+}
+
+impl Policy {
+    /// Parses and validates a policy, returning a readable error for unknown
+    /// keys, wrong types and invalid names.
+    pub fn parse(source: &str) -> Result<Self, String> {
+        let policy: Self = toml::from_str(source).map_err(|e| e.to_string())?;
+        policy.validate()?;
+        Ok(policy)
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let source = read_to_string(path)
+            .unwrap_or_else(|_| panic!("Failed to read policy file {}", path.display()));
+        Self::parse(&source)
+            .unwrap_or_else(|e| panic!("Invalid policy file {}: {e}", path.display()))
+    }
+
+    /// Checks that every `DeniedMembers` name is a scriptable member of its
+    /// service. The bundled reflection database only describes properties,
+    /// so functions and events can only be checked against `dump`.
+    pub fn check_members(&self, dump: Option<&ApiDump>) -> Result<(), String> {
+        let db = rbx_reflection_database::get_bundled();
+        for (service, members) in &self.denied_members {
+            for member in members.keys() {
+                match dump {
+                    Some(dump) if !dump.classes.iter().any(|c| c.name == *service) => {
+                        return Err(format!("DeniedMembers: '{service}' is not in the API dump"));
+                    }
+                    Some(dump) if !is_dump_member(dump, service, member) => {
+                        return Err(format!(
+                            "DeniedMembers.{service}: '{member}' is not a scriptable member of {service}"
+                        ));
+                    }
+                    None if !is_property(db, service, member) => {
+                        return Err(format!(
+                            "DeniedMembers.{service}: '{member}' is not a property of {service}; \
+                             functions and events can only be checked with --api-dump"
+                        ));
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn validate(&self) -> Result<(), String> {
+        let db = rbx_reflection_database::get_bundled();
+        let list = &self.instance_list;
+        for (table, services) in [
+            ("InstanceList.Allow", &list.allow),
+            ("InstanceList.ExplicitDisallow", &list.explicit_disallow),
+        ] {
+            // `InstanceList` resolves these with `game:GetService`
+            for name in services.keys().filter(|name| *name != "game") {
+                if !is_class_name(name) {
+                    return Err(format!("{table}: '{name}' is not a service name"));
+                }
+                match db.classes.get(name.as_str()) {
+                    Some(class) if class.tags.contains(&ClassTag::Service) => {}
+                    Some(_) => return Err(format!("{table}: '{name}' is not a service")),
+                    None => return Err(format!("{table}: '{name}' is not in the API dump")),
+                }
+            }
+        }
+
+        for (table, classes) in [
+            ("InstanceList.DisallowedClasses", &list.disallowed_classes),
+            ("InstanceList.ForbiddenClasses", &list.forbidden_classes),
+        ] {
+            for (i, class) in classes.iter().enumerate() {
+                if !is_class_name(class) {
+                    return Err(format!("{table}: '{class}' is not a class name"));
+                }
+                if classes[..i].contains(class) {
+                    return Err(format!("{table}: '{class}' is listed more than once"));
+                }
+            }
+        }
+
+        for (service, members) in &self.denied_members {
+            match db.classes.get(service.as_str()) {
+                Some(class) if class.tags.contains(&ClassTag::Service) => {}
+                Some(_) => return Err(format!("DeniedMembers: '{service}' is not a service")),
+                None => return Err(format!("DeniedMembers: '{service}' is not in the API dump")),
+            }
+            for (member, message) in members {
+                if !is_identifier(member) {
+                    return Err(format!(
+                        "DeniedMembers.{service}: '{member}' is not a member name"
+                    ));
+                }
+                if message.is_empty() {
+                    return Err(format!("DeniedMembers.{service}.{member} needs a message"));
+                }
+            }
+        }
+
+        for (name, value) in &self.globals {
+            if !is_identifier(name) || LUAU_KEYWORDS.contains(&name.as_str()) {
+                return Err(format!("Globals: '{name}' is not a valid global name"));
+            }
+            if let GlobalValue::Float(f) = value
+                && !f.is_finite()
+            {
+                return Err(format!("Globals: '{name}' must be a finite number"));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Generates the `Config` module source. `header` (the license comment)
+    /// is placed right after the directives.
+    pub fn config_source(&self, header: &str) -> String {
+        fn class_list(out: &mut String, name: &str, classes: &[String]) {
+            let classes: Vec<_> = classes.iter().map(|c| luau_string(c)).collect();
+            let items = if classes.is_empty() {
+                "{}".to_owned()
+            } else {
+                format!("{{ {} }}", classes.join(", "))
+            };
+            let _ = writeln!(out, "\t{name} = table.freeze({items} :: {{ string }}),");
+        }
+
+        let mut out = generated_header(header);
+        out.push_str("local Config = {}\n\n");
+
+        let _ = writeln!(
+            out,
+            "Config.AllowParallelLuau = {}",
+            self.allow_parallel_luau
+        );
+        let _ = writeln!(out, "Config.TrackInstances = {}", self.track_instances);
+        let _ = writeln!(
+            out,
+            "Config.TrackRBXScriptConnections = {}\n",
+            self.track_rbx_script_connections
+        );
+
+        let list = &self.instance_list;
+        out.push_str("Config.InstanceList = table.freeze({\n");
+        bool_table(&mut out, "Allow", &list.allow);
+        bool_table(&mut out, "ExplicitDisallow", &list.explicit_disallow);
+        class_list(&mut out, "DisallowedClasses", &list.disallowed_classes);
+        class_list(&mut out, "ForbiddenClasses", &list.forbidden_classes);
+        out.push_str("})\n\n");
+
+        out.push_str("Config.Globals = table.freeze({");
+        if !self.globals.is_empty() {
+            out.push('\n');
+            for (name, value) in &self.globals {
+                let _ = writeln!(out, "\t{} = {},", luau_key(name), value.to_luau());
+            }
+        }
+        out.push_str("} :: { [string]: any })\n\nreturn table.freeze(Config)\n");
+        out
+    }
+
+    /// Generates the `DeniedMembers` module source, which `Sandboxer` turns
+    /// into `__index` hooks on each service.
+    pub fn denied_members_source(&self, header: &str) -> String {
+        let mut out = generated_header(header);
+        out.push_str("return table.freeze({\n");
+        for (service, members) in &self.denied_members {
+            let _ = writeln!(out, "\t{} = table.freeze({{", luau_key(service));
+            for (member, message) in members {
+                let _ = writeln!(out, "\t\t{} = {},", luau_key(member), luau_string(message));
+            }
+            out.push_str("\t}),\n");
+        }
+        out.push_str("}) :: { [string]: { [string]: string } }\n");
+        out
+    }
+}
+
+fn generated_header(license: &str) -> String {
+    let mut out = String::with_capacity(2048);
+    out.push_str("--!strict\n--!optimize 2\n");
+    out.push_str(license);
+    out.push_str("\n-- Generated from sandboxer.toml by the builder.\n\n");
+    out
+}
+
+/// Returns the license comment (`--[[ ... ]]`) at the top of a module, after
+/// its directives.
+fn license_header(source: &str) -> &str {
+    let Some(start) = source.find("--[[") else {
+        return "";
+    };
+    source[start..]
+        .find("]]")
+        .map_or("", |end| &source[start..start + end + 2])
+}
+
+/// Replaces the source of the module `name` in `dom` with `generate`d
+/// source, keeping its license header.
+fn generate_module(dom: &mut WeakDom, name: &str, generate: impl FnOnce(&str) -> String) {
+    let root = dom.root_ref();
+    let module = dom
+        .get_by_ref(root)
+        .expect("Root does not exist")
+        .children()
+        .iter()
+        .copied()
+        .find(|&child| dom.get_by_ref(child).is_some_and(|i| i.name == name))
+        .unwrap_or_else(|| panic!("Sandboxer has no {name} module to generate"));
+
+    let inst = dom.get_by_ref_mut(module).expect("Module does not exist");
+    let source = match inst.properties.get(&ustr("Source")) {
+        Some(Variant::String(source)) => source.as_str(),
+        _ => panic!("{name} is not a ModuleScript"),
+    };
+    let generated = generate(license_header(source));
+    inst.properties
+        .insert(ustr("Source"), Variant::String(generated));
+}
+
+/// Modules of the Sandboxer tree replaced by [`apply`].
+pub const GENERATED_MODULES: [&str; 2] = ["Config", "DeniedMembers"];
+
+/// Replaces the `Config` and `DeniedMembers` modules in `dom` with ones
+/// generated from `policy`.
+pub fn apply(dom: &mut WeakDom, policy: &Policy) {
+    let [config, denied_members] = GENERATED_MODULES;
+    generate_module(dom, config, |header| policy.config_source(header));
+    generate_module(dom, denied_members, |header| {
+        policy.denied_members_source(header)
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_repository_policy() {
+        let policy = Policy::read("../sandboxer.toml");
+        assert!(!policy.allow_parallel_luau);
+        assert_eq!(policy.instance_list.allow.get("game"), Some(&false));
+        assert_eq!(policy.instance_list.allow.get("Workspace"), Some(&true));
+        assert_eq!(
+            policy
+                .instance_list
+                .explicit_disallow
+                .get("DataStoreService"),
+            Some(&false)
+        );
+    }
+
+    #[test]
+    fn repository_policy_matches_config_defaults() {
+        let policy = Policy::read("../sandboxer.toml");
+        let config = read_to_string("../src/Config.luau").unwrap();
+        let generated = policy.config_source("");
+
+        for line in generated.lines().filter(|l| l.starts_with("Config.")) {
+            if !line.ends_with("({") {
+                assert!(config.contains(line), "{line} not in src/Config.luau");
+            }
+        }
+        for line in generated.lines().filter(|l| l.starts_with('\t')) {
+            assert!(config.contains(line), "{line} not in src/Config.luau");
+        }
+    }
+
+    #[test]
+    fn repository_denied_members_module_is_up_to_date() {
+        let policy = Policy::read("../sandboxer.toml");
+        let module = read_to_string("../src/DeniedMembers.luau").unwrap();
+        assert_eq!(
+            policy.denied_members_source(license_header(&module)),
+            module.replace("\r\n", "\n")
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_keys() {
+        let err = Policy::parse("TrackInstance = true").unwrap_err();
+        assert!(err.contains("unknown field `TrackInstance`"), "{err}");
+
+        let err = Policy::parse("[InstanceList]\nAllowed = {}").unwrap_err();
+        assert!(err.contains("unknown field `Allowed`"), "{err}");
+    }
+
+    #[test]
+    fn rejects_wrong_types() {
+        let err = Policy::parse("AllowParallelLuau = \"yes\"").unwrap_err();
+        assert!(err.contains("AllowParallelLuau"), "{err}");
+    }
+
+    #[test]
+    fn rejects_invalid_names() {
+        for (source, message) in [
+            (
+                "[InstanceList.Allow]\n\"Data Store\" = true",
+                "'Data Store' is not a service name",
+            ),
+            (
+                "[InstanceList.ExplicitDisallow]\nPart = false",
+                "InstanceList.ExplicitDisallow: 'Part' is not a service",
+            ),
+            (
+                "[InstanceList.Allow]\nWorkspaceService = true",
+                "InstanceList.Allow: 'WorkspaceService' is not in the API dump",
+            ),
+            (
+                "[InstanceList]\nForbiddenClasses = [\"Fire\", \"Fire\"]",
+                "'Fire' is listed more than once",
+            ),
+            (
+                "[Globals]\n\"end\" = false",
+                "'end' is not a valid global name",
+            ),
+            ("[Globals]\nx = nan", "'x' must be a finite number"),
+            (
+                "[DeniedMembers.HttpServce]\nGetAsync = \"no\"",
+                "'HttpServce' is not in the API dump",
+            ),
+            (
+                "[DeniedMembers.Part]\nDestroy = \"no\"",
+                "'Part' is not a service",
+            ),
+            (
+                "[DeniedMembers.HttpService]\n\"Get Async\" = \"no\"",
+                "'Get Async' is not a member name",
+            ),
+            (
+                "[DeniedMembers.HttpService]\nGetAsync = \"\"",
+                "DeniedMembers.HttpService.GetAsync needs a message",
+            ),
+        ] {
+            assert!(Policy::parse(source).unwrap_err().contains(message));
+        }
+    }
+
+    #[test]
+    fn checks_denied_members_against_api_dump() {
+        let dump: ApiDump = serde_json::from_str(
+            r#"{"Classes": [
+                {"Name": "Instance", "Superclass": "<<<ROOT>>>", "Members": [
+                    {"MemberType": "Function", "Name": "Destroy", "Security": "None"}
+                ]},
+                {"Name": "Players", "Superclass": "Instance", "Members": [
+                    {"MemberType": "Function", "Name": "BanAsync", "Security": "None"},
+                    {"MemberType": "Function", "Name": "Internal", "Security": "RobloxScriptSecurity"}
+                ]}
+            ]}"#,
+        )
+        .unwrap();
+        let check = |member: &str, dump| {
+            Policy::parse(&format!("[DeniedMembers.Players]\n{member} = \"no\""))
+                .unwrap()
+                .check_members(dump)
+        };
+
+        assert_eq!(check("BanAsync", Some(&dump)), Ok(()));
+        assert_eq!(check("Destroy", Some(&dump)), Ok(()));
+        assert_eq!(
+            check("Internal", Some(&dump)).unwrap_err(),
+            "DeniedMembers.Players: 'Internal' is not a scriptable member of Players"
+        );
+        assert!(check("BanAsnyc", Some(&dump)).is_err());
+        // properties are in the bundled database; functions need the dump
+        assert_eq!(check("MaxPlayers", None), Ok(()));
+        assert!(
+            check("BanAsync", None)
+                .unwrap_err()
+                .contains("can only be checked with --api-dump")
+        );
+
+        let policy = Policy::parse("[DeniedMembers.HttpService]\nGetAsync = \"no\"").unwrap();
+        assert_eq!(
+            policy.check_members(Some(&dump)).unwrap_err(),
+            "DeniedMembers: 'HttpService' is not in the API dump"
+        );
+    }
+
+    #[test]
+    fn generates_config_module() {
+        let policy = Policy::parse(
+            "TrackInstances = true\n\
+             [InstanceList]\nForbiddenClasses = [\"Fire\"]\n\
+             [InstanceList.Allow]\ngame = false\n\
+             [Globals]\ngame = false\ngreeting = \"hi \\\"there\\\"\"\nlimit = 5",
+        )
+        .unwrap();
+        let source = policy.config_source("--[[ license ]]");
+
+        assert!(source.starts_with("--!strict\n--!optimize 2\n--[[ license ]]\n"));
+        assert!(source.contains("Config.TrackInstances = true\n"));
+        assert!(source.contains("\t\tgame = false,\n"));
+        assert!(source.contains("\tForbiddenClasses = table.freeze({ \"Fire\" } :: { string }),"));
+        assert!(source.contains("\tgreeting = \"hi \\\"there\\\"\",\n\tlimit = 5,\n"));
+        assert!(source.ends_with("return table.freeze(Config)\n"));
+    }
+
+    #[test]
+    fn finds_license_header() {
+        assert_eq!(
+            license_header("--!strict\n--[[\nLICENSE\n]]\n\nlocal x = {}"),
+            "--[[\nLICENSE\n]]"
+        );
+        assert_eq!(license_header("return nil"), "");
+    }
+}
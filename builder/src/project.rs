@@ -0,0 +1,274 @@
+use std::{
+    collections::BTreeMap,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use rbx_dom_weak::{InstanceBuilder, types::Variant};
+
+/// The file each script was read from, keyed by its full name
+/// (`Sandboxer.InstanceList`).
+pub type SourceMap = BTreeMap<String, PathBuf>;
+
+/// A Rojo project file (`default.project.json`). Only the parts of the
+/// format needed to build a model are supported: `$className`, `$path`,
+/// `$properties` with primitive values, and nested children.
+#[derive(serde::Deserialize, Debug)]
+pub struct Project {
+    pub name: String,
+    pub tree: ProjectNode,
+}
+
+#[derive(serde::Deserialize, Debug)]
+pub struct ProjectNode {
+    #[serde(rename = "$className")]
+    pub class_name: Option<String>,
+    #[serde(rename = "$path")]
+    pub path: Option<PathBuf>,
+    #[serde(rename = "$properties", default)]
+    pub properties: BTreeMap<String, serde_json::Value>,
+    /// Accepted for compatibility with Rojo; has no effect on a model build.
+    #[expect(dead_code)]
+    #[serde(rename = "$ignoreUnknownInstances", default)]
+    pub ignore_unknown_instances: bool,
+    #[serde(flatten, deserialize_with = "children")]
+    pub children: BTreeMap<String, ProjectNode>,
+}
+
+/// Children of a node. Keys starting with `$` are properties of the node, so
+/// one that is not supported is an error rather than a child.
+fn children<'de, D>(deserializer: D) -> Result<BTreeMap<String, ProjectNode>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    use serde::de::Error;
+
+    let children: BTreeMap<String, serde_json::Value> =
+        serde::Deserialize::deserialize(deserializer)?;
+    children
+        .into_iter()
+        .map(|(name, node)| {
+            if name.starts_with('$') {
+                return Err(D::Error::custom(format!(
+                    "unsupported project key `{name}`"
+                )));
+            }
+            let node = serde_json::from_value(node)
+                .map_err(|e| D::Error::custom(format!("in `{name}`: {e}")))?;
+            Ok((name, node))
+        })
+        .collect()
+}
+
+impl Project {
+    pub fn read<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        let source = read_to_string(path)
+            .unwrap_or_else(|_| panic!("Failed to read project file {}", path.display()));
+        serde_json::from_str(&source)
+            .unwrap_or_else(|e| panic!("Invalid project file {}: {e}", path.display()))
+    }
+
+    /// Builds the instance tree. `$path`s are resolved relative to `base`,
+    /// the directory containing the project file. Also returns where each
+    /// script came from, so coverage and errors can be mapped back to the
+    /// repository.
+    pub fn build(&self, base: &Path) -> (InstanceBuilder, SourceMap) {
+        let mut sources = SourceMap::new();
+        let builder = self.tree.build(&self.name, None, base, &mut sources);
+        (builder, sources)
+    }
+}
+
+fn full_name(parent: Option<&str>, name: &str) -> String {
+    match parent {
+        Some(parent) => format!("{parent}.{name}"),
+        None => name.to_owned(),
+    }
+}
+
+impl ProjectNode {
+    fn build(
+        &self,
+        name: &str,
+        parent: Option<&str>,
+        base: &Path,
+        sources: &mut SourceMap,
+    ) -> InstanceBuilder {
+        let full_name = full_name(parent, name);
+        let mut builder = match (&self.path, &self.class_name) {
+            (Some(path), _) => instance_from_path(&base.join(path), Some(name), parent, sources)
+                .unwrap_or_else(|| panic!("$path {} is not a script or folder", path.display())),
+            (None, Some(class_name)) => InstanceBuilder::new(class_name.as_str()).with_name(name),
+            (None, None) => panic!("Project node '{name}' needs a $className or $path"),
+        };
+
+        for (key, value) in &self.properties {
+            let variant = match value {
+                serde_json::Value::String(s) => Variant::String(s.clone()),
+                serde_json::Value::Bool(b) => Variant::Bool(*b),
+                serde_json::Value::Number(n) => Variant::Float64(n.as_f64().unwrap_or_default()),
+                _ => panic!("Unsupported value for property {key} of '{name}'"),
+            };
+            builder = builder.with_property(key.as_str(), variant);
+        }
+
+        builder.with_children(
+            self.children.iter().map(|(child_name, child)| {
+                child.build(child_name, Some(&full_name), base, sources)
+            }),
+        )
+    }
+}
+
+/// Returns the script class and instance name for a script file name,
+/// following Rojo's `.server.luau` / `.client.luau` conventions.
+fn script_class(file_name: &str) -> Option<(&'static str, &str)> {
+    let stem = file_name
+        .strip_suffix(".luau")
+        .or_else(|| file_name.strip_suffix(".lua"))?;
+    Some(if let Some(stem) = stem.strip_suffix(".server") {
+        ("Script", stem)
+    } else if let Some(stem) = stem.strip_suffix(".client") {
+        ("LocalScript", stem)
+    } else {
+        ("ModuleScript", stem)
+    })
+}
+
+fn read_file(path: &Path) -> String {
+    read_to_string(path).unwrap_or_else(|_| panic!("Failed to read {}", path.display()))
+}
+
+/// Builds an instance from a file or directory. A directory containing an
+/// `init` script becomes that script, with the rest of the directory as its
+/// children; any other directory becomes a `Folder`. Files that are not
+/// scripts or `.txt` files are skipped.
+fn instance_from_path(
+    path: &Path,
+    name: Option<&str>,
+    parent: Option<&str>,
+    sources: &mut SourceMap,
+) -> Option<InstanceBuilder> {
+    let file_name = path.file_name()?.to_string_lossy();
+
+    if !path.is_dir() {
+        if let Some((class, stem)) = script_class(&file_name) {
+            sources.insert(full_name(parent, name.unwrap_or(stem)), path.to_owned());
+            return Some(
+                InstanceBuilder::with_property_capacity(class, 1)
+                    .with_name(name.unwrap_or(stem))
+                    .with_property("Source", read_file(path)),
+            );
+        }
+        let stem = file_name.strip_suffix(".txt")?;
+        return Some(
+            InstanceBuilder::with_property_capacity("StringValue", 1)
+                .with_name(name.unwrap_or(stem))
+                .with_property("Value", read_file(path)),
+        );
+    }
+
+    let mut entries: Vec<_> = read_dir(path)
+        .unwrap_or_else(|_| panic!("Failed to read directory {}", path.display()))
+        .map(|entry| entry.expect("Failed to read directory entry").path())
+        .collect();
+    entries.sort_unstable();
+
+    let init = entries.iter().position(|entry| {
+        entry.is_file()
+            && entry
+                .file_name()
+                .and_then(|n| n.to_str())
+                .and_then(script_class)
+                .is_some_and(|(_, stem)| stem == "init")
+    });
+
+    let name = name.unwrap_or(&file_name);
+    let full_name = full_name(parent, name);
+    let builder = match init {
+        Some(index) => {
+            let init = entries.remove(index);
+            sources.insert(full_name.clone(), init.clone());
+            // `position` above only matches script file names
+            let (class, _) = script_class(&init.file_name().unwrap().to_string_lossy()).unwrap();
+            InstanceBuilder::with_property_capacity(class, 1)
+                .with_name(name)
+                .with_property("Source", read_file(&init))
+        }
+        None => InstanceBuilder::new("Folder").with_name(name),
+    };
+
+    Some(
+        builder.with_children(
+            entries
+                .iter()
+                .filter_map(|entry| instance_from_path(entry, None, Some(&full_name), sources)),
+        ),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn classifies_script_files() {
+        assert_eq!(script_class("init.luau"), Some(("ModuleScript", "init")));
+        assert_eq!(script_class("Main.server.luau"), Some(("Script", "Main")));
+        assert_eq!(script_class("Ui.client.lua"), Some(("LocalScript", "Ui")));
+        assert_eq!(script_class("README.md"), None);
+    }
+
+    #[test]
+    fn rejects_unknown_dollar_keys() {
+        let error = serde_json::from_str::<Project>(
+            r#"{"name": "M", "tree": {"$path": "src", "$ignoreUnknownInstance": true}}"#,
+        )
+        .unwrap_err();
+        assert!(
+            error.to_string().contains("`$ignoreUnknownInstance`"),
+            "{error}"
+        );
+    }
+
+    #[test]
+    fn builds_repository_project() {
+        let project = Project::read("../default.project.json");
+        let dom = rbx_dom_weak::WeakDom::new(project.build(Path::new("..")).0);
+        let root = dom.get_by_ref(dom.root_ref()).unwrap();
+        assert_eq!(root.name, "Sandboxer");
+        assert_eq!(root.class, "ModuleScript");
+
+        let mut children: Vec<_> = root
+            .children()
+            .iter()
+            .map(|r| dom.get_by_ref(*r).unwrap().name.as_str())
+            .collect();
+        children.sort_unstable();
+        assert_eq!(
+            children,
+            [
+                "Config",
+                "DeniedMembers",
+                "InstanceList",
+                "InstanceSandboxer"
+            ]
+        );
+    }
+
+    #[test]
+    fn maps_scripts_to_files() {
+        let project = Project::read("../default.project.json");
+        let (_, sources) = project.build(Path::new(".."));
+        assert_eq!(
+            sources.get("Sandboxer"),
+            Some(&Path::new("../src/init.luau").to_owned())
+        );
+        assert_eq!(
+            sources.get("Sandboxer.InstanceSandboxer"),
+            Some(&Path::new("../src/InstanceSandboxer.luau").to_owned())
+        );
+        assert_eq!(sources.len(), 5);
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    SandboxerBuild, args::Args, json::LuauExecutionTaskResult, macros::get_iso8601, open_cloud,
+    output,
+};
+
+const MODEL: &str = "Sandboxer.rbxm";
+pub const MANIFEST_FILE: &str = "release.json";
+/// How long to wait for the upload to be processed without `--deadline`.
+const PUBLISH_TIMEOUT: Duration = Duration::from_secs(10 * 60);
+
+/// A build of `Sandboxer.rbxm` whose full test suite passed.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestedBuild {
+    pub version: String,
+    pub commit: String,
+    pub tested_at: String,
+}
+
+/// What was published, attached to the GitHub release.
+#[derive(Serialize, Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct ReleaseManifest {
+    pub version: String,
+    pub commit: String,
+    /// SHA-256 of the published `Sandboxer.rbxm`.
+    pub sha256: String,
+    pub asset_id: u64,
+    /// Revision of the asset created by the upload.
+    pub asset_version: String,
+    pub published_at: String,
+}
+
+/// Tested builds keyed by the SHA-256 of `Sandboxer.rbxm`; a missing file
+/// means nothing was tested.
+fn read_tested(path: &Path) -> BTreeMap<String, TestedBuild> {
+    match fs::read_to_string(path) {
+        Ok(json) => serde_json::from_str(&json)
+            .unwrap_or_else(|e| panic!("Invalid tested builds file {}: {e}", path.display())),
+        Err(_) => BTreeMap::new(),
+    }
+}
+
+/// Records that the tests passed for `build`, so it can be published. Runs
+/// that only selected some of the tests do not count.
+pub fn record_tested(args: &Args, build: &SandboxerBuild, result: &LuauExecutionTaskResult) {
+    if !result.success {
+        return;
+    }
+    if !(args.filter.is_empty() && args.suite.is_empty() && args.exclude.is_empty()) {
+        debug!(
+            "Not recording a partial test run in {}",
+            args.tested.display()
+        );
+        return;
+    }
+    let path: &Path = &args.tested;
+    let mut tested = read_tested(path);
+    tested.insert(
+        build.sha256.clone(),
+        TestedBuild {
+            version: build.build_info.version.clone(),
+            commit: build.build_info.commit.clone(),
+            tested_at: get_iso8601(),
+        },
+    );
+    let json = serde_json::to_string_pretty(&tested).expect("Failed to serialize tested builds");
+    let written = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|()| fs::write(path, json));
+    match written {
+        Ok(()) => info!("Recorded {MODEL} ({}) as tested", build.sha256),
+        Err(e) => warn!("Failed to write {}: {e}", path.display()),
+    }
+}
+
+pub fn run(args: &Args) {
+    let Some(asset_id) = args.asset_id else {
+        panic!("publish needs the asset to upload to (--asset-id)");
+    };
+    let rbxm = fs::read(MODEL).unwrap_or_else(|e| panic!("Failed to read {MODEL}: {e}"));
+    let sha256 = output::sha256_hex(&rbxm);
+    let Some(build) = read_tested(&args.tested).remove(&sha256) else {
+        panic!(
+            "Tests have not passed for {MODEL} ({sha256}); run the full test suite on this build before publishing"
+        );
+    };
+
+    let client = open_cloud(args);
+    let deadline = Instant::now() + args.deadline.unwrap_or(PUBLISH_TIMEOUT);
+    info!(
+        "Publishing Sandboxer {} ({sha256}) to asset {asset_id}...",
+        build.version
+    );
+    let operation = client
+        .update_asset(asset_id, MODEL, rbxm)
+        .unwrap_or_else(|e| panic!("Asset update request failed: {e}"));
+    let asset = client
+        .wait_for_operation(operation, Some(deadline), |delay| {
+            info!(
+                "Upload is still processing. Waiting {} seconds before polling again...",
+                delay.as_secs()
+            )
+        })
+        .unwrap_or_else(|e| panic!("Publishing failed: {e}"));
+
+    let manifest = ReleaseManifest {
+        version: build.version,
+        commit: build.commit,
+        sha256,
+        asset_id,
+        asset_version: asset.revision_id,
+        published_at: get_iso8601(),
+    };
+    let json =
+        serde_json::to_string_pretty(&manifest).expect("Failed to serialize release manifest");
+    fs::write(MANIFEST_FILE, json)
+        .unwrap_or_else(|e| panic!("Failed to write {MANIFEST_FILE}: {e}"));
+    info!(
+        "Published asset {asset_id} version {}; wrote {MANIFEST_FILE}",
+        manifest.asset_version
+    );
+}
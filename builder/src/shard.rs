@@ -0,0 +1,107 @@
+use std::fmt;
+
+use crate::json::LuauExecutionTaskResult;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Shard {
+    pub index: usize,
+    pub count: usize,
+}
+
+impl Shard {
+    /// File name of the test binary for this shard. A single shard keeps
+    /// the original `test.rbxm` name.
+    pub fn file_name(self) -> String {
+        if self.count == 1 {
+            "test.rbxm".to_owned()
+        } else {
+            format!("test-{}.rbxm", self.index + 1)
+        }
+    }
+}
+
+impl fmt::Display for Shard {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "shard {}/{}", self.index + 1, self.count)
+    }
+}
+
+/// Splits `items` round-robin into at most `count` non-empty shards.
+pub fn split<T>(items: Vec<T>, count: usize) -> Vec<Vec<T>> {
+    let count = count.clamp(1, items.len().max(1));
+    let mut shards: Vec<Vec<T>> = (0..count).map(|_| Vec::new()).collect();
+    for (i, item) in items.into_iter().enumerate() {
+        shards[i % count].push(item);
+    }
+    shards
+}
+
+/// Merges per-shard results. Shards run concurrently, so the merged time
+/// is that of the slowest shard.
+pub fn merge_results<I: IntoIterator<Item = LuauExecutionTaskResult>>(
+    results: I,
+) -> LuauExecutionTaskResult {
+    results.into_iter().fold(
+        LuauExecutionTaskResult {
+            suites: 0,
+            total: 0,
+            passed: 0,
+            failed: 0,
+            skipped: 0,
+            success: true,
+            time: 0.0,
+            benchmarks: Vec::new(),
+        },
+        |acc, result| LuauExecutionTaskResult {
+            suites: acc.suites + result.suites,
+            total: acc.total + result.total,
+            passed: acc.passed + result.passed,
+            failed: acc.failed + result.failed,
+            skipped: acc.skipped + result.skipped,
+            success: acc.success && result.success,
+            time: acc.time.max(result.time),
+            benchmarks: Vec::new(),
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn result(passed: u32, failed: u32, time: f64) -> LuauExecutionTaskResult {
+        LuauExecutionTaskResult {
+            suites: 1,
+            total: passed + failed,
+            passed,
+            failed,
+            skipped: 0,
+            success: failed == 0,
+            time,
+            benchmarks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn splits_round_robin() {
+        assert_eq!(split(vec![1, 2, 3, 4, 5], 2), [vec![1, 3, 5], vec![2, 4]]);
+    }
+
+    #[test]
+    fn never_creates_empty_shards() {
+        assert_eq!(split(vec![1, 2], 8), [vec![1], vec![2]]);
+        assert_eq!(split(vec![1, 2], 0), [vec![1, 2]]);
+        assert_eq!(split(Vec::<i32>::new(), 4), [Vec::<i32>::new()]);
+    }
+
+    #[test]
+    fn merges_results() {
+        let merged = merge_results([result(3, 0, 1.5), result(2, 1, 0.5)]);
+        assert_eq!(merged.suites, 2);
+        assert_eq!(merged.total, 6);
+        assert_eq!(merged.passed, 5);
+        assert_eq!(merged.failed, 1);
+        assert!(!merged.success);
+        assert!((merged.time - 1.5).abs() < f64::EPSILON);
+    }
+}
@@ -0,0 +1,126 @@
+use std::{env::var as env, fs::read_to_string, process::Command};
+
+use rbx_dom_weak::types::Attributes;
+use time::UtcDateTime;
+
+use crate::macros::{format_iso8601, get_iso8601};
+
+/// Version and provenance stamped onto the built `Sandboxer` ModuleScript.
+#[derive(Debug, PartialEq, Eq)]
+pub struct BuildInfo {
+    pub version: String,
+    pub commit: String,
+    pub build_time: String,
+}
+
+fn git(args: &[&str]) -> Option<String> {
+    let output = Command::new("git").args(args).output().ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let out = String::from_utf8(output.stdout).ok()?;
+    let out = out.trim();
+    (!out.is_empty()).then(|| out.to_owned())
+}
+
+/// Turns `git describe` output (`v1.6.5` or `v1.6.5-3-gabc1234`) into a
+/// version without the `v` prefix.
+fn version_from_tag(describe: &str) -> Option<String> {
+    describe.strip_prefix('v').map(ToOwned::to_owned)
+}
+
+/// Reads the version from the README heading (`# [v1.6.5] Luau Sandboxer`).
+fn version_from_readme(readme: &str) -> Option<String> {
+    let heading = readme.lines().next()?.strip_prefix("# [v")?;
+    let (version, _) = heading.split_once(']')?;
+    Some(version.to_owned())
+}
+
+impl BuildInfo {
+    /// Detects build info from git, falling back to the README for the
+    /// version. Reproducible builds use `SOURCE_DATE_EPOCH` or the commit
+    /// time instead of the current time.
+    pub fn detect(version_override: Option<&str>, reproducible: bool) -> Self {
+        let source_date_epoch = env("SOURCE_DATE_EPOCH").ok();
+        Self::detect_with(version_override, reproducible, source_date_epoch.as_deref())
+    }
+
+    fn detect_with(
+        version_override: Option<&str>,
+        reproducible: bool,
+        source_date_epoch: Option<&str>,
+    ) -> Self {
+        let version = version_override
+            .map(ToOwned::to_owned)
+            .or_else(|| {
+                git(&["describe", "--tags", "--match", "v*"]).and_then(|d| version_from_tag(&d))
+            })
+            .or_else(|| {
+                read_to_string("README.md")
+                    .ok()
+                    .and_then(|r| version_from_readme(&r))
+            })
+            .unwrap_or_else(|| "dev".to_owned());
+
+        let commit = env("GITHUB_SHA")
+            .ok()
+            .or_else(|| git(&["rev-parse", "HEAD"]))
+            .unwrap_or_else(|| "unknown".to_owned());
+
+        let build_time = if reproducible {
+            source_date_epoch
+                .map(ToOwned::to_owned)
+                .or_else(|| git(&["log", "-1", "--format=%ct"]))
+                .and_then(|epoch| epoch.parse::<i64>().ok())
+                .and_then(|epoch| UtcDateTime::from_unix_timestamp(epoch).ok())
+                .map_or_else(|| format_iso8601(UtcDateTime::UNIX_EPOCH), format_iso8601)
+        } else {
+            get_iso8601()
+        };
+
+        Self {
+            version,
+            commit,
+            build_time,
+        }
+    }
+
+    pub fn attributes(&self) -> Attributes {
+        Attributes::new()
+            .with("Version", self.version.as_str())
+            .with("Commit", self.commit.as_str())
+            .with("BuildTime", self.build_time.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_version_from_tag() {
+        assert_eq!(version_from_tag("v1.6.5").as_deref(), Some("1.6.5"));
+        assert_eq!(
+            version_from_tag("v1.6.5-3-gabc1234").as_deref(),
+            Some("1.6.5-3-gabc1234")
+        );
+        assert_eq!(version_from_tag("abc1234"), None);
+    }
+
+    #[test]
+    fn reads_version_from_readme() {
+        assert_eq!(
+            version_from_readme("# [v1.6.5] Luau Sandboxer\nblah").as_deref(),
+            Some("1.6.5")
+        );
+        assert_eq!(version_from_readme("# Luau Sandboxer"), None);
+    }
+
+    #[test]
+    fn reproducible_build_time_uses_source_date_epoch() {
+        let info = BuildInfo::detect_with(Some("1.2.3"), true, Some("1700000000"));
+
+        assert_eq!(info.version, "1.2.3");
+        assert_eq!(info.build_time, "2023-11-14T22:13:20.000Z");
+    }
+}
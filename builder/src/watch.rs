@@ -0,0 +1,528 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    panic::{AssertUnwindSafe, catch_unwind},
+    path::{Path, PathBuf},
+    sync::{Mutex, mpsc::Receiver},
+    time::Duration,
+};
+
+use notify::{EventKind, RecursiveMode, Watcher};
+use rbx_dom_weak::{
+    WeakDom,
+    types::{Ref, Variant},
+    ustr,
+};
+
+use crate::{
+    SandboxerBuild,
+    args::Args,
+    binary_cache::BinaryCache,
+    build_sandboxer_dom, build_test_rbxm, cancel,
+    json::{LuauExecutionTaskOutput, LuauExecutionTaskState},
+    lexer::tokenize,
+    logs::LogOptions,
+    minify, open_cloud, print_results, read_test_modules, run_shard,
+    shard::Shard,
+    stream_and_print_logs, test_script_paths, write_test_rbxm,
+};
+
+/// The test runner and test modules, as read by `build_test_rbxm`.
+const RUNNER_DIR: &str = "./builder/src/luau";
+const TESTS_DIR: &str = "./builder/src/luau/scripts";
+
+/// How long to wait for more events after a change; editors often save a
+/// file in several steps.
+const DEBOUNCE: Duration = Duration::from_millis(200);
+
+const SHARD: Shard = Shard { index: 0, count: 1 };
+
+/// Where a script sits in the test model (`Sandboxer-Tests.Sandboxer.Config`),
+/// for resolving `require` paths. Test module names contain dots, so this is
+/// not a joined string.
+type ModulePath = Vec<String>;
+
+/// Mirrors the layout of the model built by `build_test_rbxm`.
+fn sandboxer_path(full_name: &str) -> ModulePath {
+    ["Sandboxer-Tests"]
+        .into_iter()
+        .chain(full_name.split('.'))
+        .map(str::to_owned)
+        .collect()
+}
+
+fn test_path(name: &str) -> ModulePath {
+    ["Sandboxer-Tests", "RunTests", "tests", name]
+        .map(str::to_owned)
+        .to_vec()
+}
+
+/// String literal paths passed to `require`, e.g. `"./Config"`.
+fn required_paths(source: &str) -> Vec<&str> {
+    let tokens = tokenize(source);
+    let mut paths = Vec::new();
+    for (index, token) in tokens.iter().enumerate() {
+        if token.text != "require" {
+            continue;
+        }
+        let mut next = tokens[index + 1..].iter().map(|token| token.text);
+        let argument = match next.next() {
+            Some("(") => next.next(),
+            argument => argument,
+        };
+        if let Some(literal) = argument
+            && literal.len() >= 2
+            && (literal.starts_with('"') || literal.starts_with('\''))
+        {
+            paths.push(&literal[1..literal.len() - 1]);
+        }
+    }
+    paths
+}
+
+/// Resolves a require-by-string path relative to the script at `from`.
+/// Aliases other than `@self` are not resolved.
+fn resolve(from: &[String], path: &str) -> Option<ModulePath> {
+    let mut resolved = from.to_vec();
+    let mut segments = path.split('/');
+    match segments.next()? {
+        "@self" => {}
+        "." => {
+            resolved.pop()?;
+        }
+        ".." => {
+            resolved.pop()?;
+            resolved.pop()?;
+        }
+        _ => return None,
+    }
+    for segment in segments {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                resolved.pop()?;
+            }
+            name => resolved.push(name.to_owned()),
+        }
+    }
+    Some(resolved)
+}
+
+/// Names of the test modules that require any of `changed`, directly or
+/// through other modules.
+fn affected_tests(
+    modules: &[(ModulePath, &str)],
+    tests: &[(String, String)],
+    changed: &BTreeSet<ModulePath>,
+) -> BTreeSet<String> {
+    let graph: BTreeMap<&[String], Vec<ModulePath>> = modules
+        .iter()
+        .map(|(path, source)| {
+            let requires = required_paths(source)
+                .into_iter()
+                .filter_map(|required| resolve(path, required))
+                .collect();
+            (path.as_slice(), requires)
+        })
+        .collect();
+
+    let mut affected = BTreeSet::new();
+    for (name, source) in tests {
+        let path = test_path(name);
+        let mut stack: Vec<ModulePath> = required_paths(source)
+            .into_iter()
+            .filter_map(|required| resolve(&path, required))
+            .collect();
+        let mut seen = BTreeSet::new();
+        while let Some(module) = stack.pop() {
+            if changed.contains(&module) {
+                affected.insert(name.clone());
+                break;
+            }
+            if let Some(requires) = graph.get(module.as_slice()) {
+                stack.extend(requires.iter().filter(|r| !seen.contains(*r)).cloned());
+            }
+            seen.insert(module);
+        }
+    }
+    affected
+}
+
+enum Change {
+    /// A script of the Sandboxer tree, by full name.
+    Script(String),
+    /// A test module, by name.
+    Test(String),
+    /// The test runner or framework; every test is rerun.
+    Runner,
+    /// An added, removed or generated script, or the policy file; the tree
+    /// is rebuilt.
+    Rebuild,
+}
+
+fn classify(
+    path: &Path,
+    scripts: &BTreeMap<PathBuf, String>,
+    runner_dir: &Path,
+    tests_dir: &Path,
+    policy: &Path,
+) -> Option<Change> {
+    if path == policy {
+        return Some(Change::Rebuild);
+    }
+    let extension = path.extension()?.to_str()?;
+    if !matches!(extension, "luau" | "lua") {
+        return None;
+    }
+    Some(match scripts.get(path) {
+        Some(name) if path.exists() => Change::Script(name.clone()),
+        _ if path.parent() == Some(tests_dir) => {
+            // matches the names `read_test_modules` gives test modules
+            let name = path.file_name()?.to_string_lossy().replace(".luau", "");
+            Change::Test(name)
+        }
+        _ if path.starts_with(runner_dir) => Change::Runner,
+        _ => Change::Rebuild,
+    })
+}
+
+fn canonical(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|e| panic!("Failed to resolve {}: {e}", path.display()))
+}
+
+/// The script of `dom` with the given full name (`Sandboxer.InstanceList`).
+fn find_script(dom: &WeakDom, full_name: &str) -> Option<Ref> {
+    let mut names = full_name.split('.');
+    let mut referent = dom.root_ref();
+    if dom.get_by_ref(referent)?.name != names.next()? {
+        return None;
+    }
+    for name in names {
+        referent = dom
+            .get_by_ref(referent)?
+            .children()
+            .iter()
+            .copied()
+            .find(|&child| dom.get_by_ref(child).is_some_and(|i| i.name == name))?;
+    }
+    Some(referent)
+}
+
+fn script_source<'a>(dom: &'a WeakDom, full_name: &str) -> Option<&'a str> {
+    let inst = dom.get_by_ref(find_script(dom, full_name)?)?;
+    match inst.properties.get(&ustr("Source")) {
+        Some(Variant::String(source)) => Some(source),
+        _ => None,
+    }
+}
+
+/// Replaces the source of one script with the file's current contents.
+fn update_script(build: &mut SandboxerBuild, full_name: &str, path: &Path, minify: bool) {
+    let mut source =
+        fs::read_to_string(path).unwrap_or_else(|_| panic!("Failed to read {}", path.display()));
+    if minify {
+        let minified = minify::minify(&source);
+        source = minified.source;
+        build
+            .line_maps
+            .insert(full_name.to_owned(), minified.line_map);
+    }
+    let referent = find_script(&build.dom, full_name)
+        .unwrap_or_else(|| panic!("{full_name} is not in the Sandboxer tree"));
+    build
+        .dom
+        .get_by_ref_mut(referent)
+        .expect("Script does not exist")
+        .properties
+        .insert(ustr("Source"), Variant::String(source));
+}
+
+/// Waits for file changes, returning every path changed within
+/// [`DEBOUNCE`] of the last one.
+fn wait_for_changes(rx: &Receiver<notify::Result<notify::Event>>) -> BTreeSet<PathBuf> {
+    let mut paths = BTreeSet::new();
+    let mut next = rx.recv().ok();
+    while let Some(event) = next {
+        match event {
+            Ok(event) if !matches!(event.kind, EventKind::Access(_)) => paths.extend(event.paths),
+            Ok(_) => {}
+            Err(e) => warn!("File watcher error: {e}"),
+        }
+        next = if paths.is_empty() {
+            rx.recv().ok()
+        } else {
+            rx.recv_timeout(DEBOUNCE).ok()
+        };
+    }
+    paths
+}
+
+struct Session<'a> {
+    args: &'a Args,
+    client: opencloud::Client,
+    log_options: LogOptions,
+    /// Unchanged test binaries are not uploaded again.
+    cache: Mutex<BinaryCache>,
+}
+
+impl Session<'_> {
+    /// Runs the selected test modules, or all of them. Failures are reported
+    /// without stopping the watch.
+    fn test(&self, build: &SandboxerBuild, selection: Option<&BTreeSet<String>>) {
+        let tests: Vec<_> = read_test_modules()
+            .into_iter()
+            .filter(|(name, _)| selection.is_none_or(|selection| selection.contains(name)))
+            .collect();
+        let names: Vec<_> = tests.iter().map(|(name, _)| name.as_str()).collect();
+        info!("Running {}", names.join(", "));
+
+        let scripts = test_script_paths(build, &tests);
+        let buf = build_test_rbxm(&build.dom, self.args, tests);
+        write_test_rbxm(&buf, SHARD);
+        let timeout = &self.args.timeout;
+        let (id, result) = run_shard(&self.client, &buf, timeout, SHARD, None, &self.cache);
+        stream_and_print_logs(&self.client, &id, SHARD, &self.log_options, &scripts);
+        match (result.state, result.output) {
+            (
+                LuauExecutionTaskState::Complete,
+                Some(LuauExecutionTaskOutput { results: [result] }),
+            ) => print_results(&result),
+            (LuauExecutionTaskState::Failed, _) => match result.error {
+                Some(err) => error!("Luau execution session failed: {}", err.message),
+                None => error!("Luau execution session failed for unknown reason"),
+            },
+            (state, _) => error!("Luau execution session ended without results (state: {state:?})"),
+        }
+    }
+}
+
+/// Runs `f`, reporting a panic instead of ending the watch.
+fn recover<T>(f: impl FnOnce() -> T) -> Option<T> {
+    let result = catch_unwind(AssertUnwindSafe(f)).ok();
+    if result.is_none() {
+        warn!("Fix the error above and save to try again");
+    }
+    result
+}
+
+/// `sandboxer-builder watch`: runs the tests, then reruns the affected test
+/// modules whenever a Sandboxer script or test changes, or everything when
+/// the policy file changes. Only changed scripts are rebuilt, and unchanged
+/// test binaries are reused from the binary input cache.
+pub fn run(args: &Args) {
+    let client = open_cloud(args);
+    cancel::install_handler(client.clone());
+
+    let mut build = build_sandboxer_dom(args);
+    let runner_dir = canonical(Path::new(RUNNER_DIR));
+    let tests_dir = canonical(Path::new(TESTS_DIR));
+    // the policy file may not exist yet, so its directory is resolved instead
+    let policy_path = args.policy_path();
+    let policy_dir = canonical(match policy_path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    });
+    let policy = policy_dir.join(policy_path.file_name().expect("Invalid policy path"));
+
+    let (tx, rx) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(tx).expect("Failed to start the file watcher");
+    let mut dirs: Vec<_> = build
+        .sources
+        .values()
+        .filter_map(|path| path.parent())
+        .map(canonical)
+        .chain([runner_dir.clone()])
+        .collect();
+    dirs.sort_unstable();
+    // watching a directory also watches everything below it
+    dirs.dedup_by(|dir, parent| dir.starts_with(parent));
+    for dir in &dirs {
+        watcher
+            .watch(dir, RecursiveMode::Recursive)
+            .unwrap_or_else(|e| panic!("Failed to watch {}: {e}", dir.display()));
+        debug!("Watching {}", dir.display());
+    }
+    watcher
+        .watch(&policy_dir, RecursiveMode::NonRecursive)
+        .unwrap_or_else(|e| panic!("Failed to watch {}: {e}", policy_dir.display()));
+
+    let session = Session {
+        args,
+        client,
+        log_options: LogOptions::new(&args.log_ignore, args.log_level, args.log_format),
+        cache: Mutex::new(BinaryCache::open(args.binary_cache.as_deref())),
+    };
+    recover(|| session.test(&build, None));
+
+    loop {
+        info!("Watching for changes (Ctrl+C to stop)...");
+        let paths = wait_for_changes(&rx);
+        let scripts: BTreeMap<_, _> = build
+            .sources
+            .iter()
+            .filter_map(|(name, path)| Some((fs::canonicalize(path).ok()?, name.clone())))
+            .collect();
+
+        let (mut changed, mut changed_tests) = (BTreeSet::new(), BTreeSet::new());
+        let (mut rerun_all, mut rebuild) = (false, false);
+        for path in &paths {
+            match classify(path, &scripts, &runner_dir, &tests_dir, &policy) {
+                Some(Change::Script(name)) => {
+                    info!("Changed: {}", path.display());
+                    changed.insert(name);
+                }
+                Some(Change::Test(name)) => {
+                    changed_tests.insert(name);
+                }
+                Some(Change::Runner) => rerun_all = true,
+                Some(Change::Rebuild) => rebuild = true,
+                None => {}
+            }
+        }
+        if changed.is_empty() && changed_tests.is_empty() && !rerun_all && !rebuild {
+            continue;
+        }
+
+        let updated = recover(|| {
+            if rebuild {
+                info!("Scripts or the policy changed; rebuilding Sandboxer");
+                build = build_sandboxer_dom(args);
+                return;
+            }
+            for name in &changed {
+                let path = build.sources[name].clone();
+                update_script(&mut build, name, &path, args.minify);
+            }
+        });
+        if updated.is_none() {
+            continue;
+        }
+
+        let selection = if rebuild || rerun_all {
+            None
+        } else {
+            let modules: Vec<_> = build
+                .sources
+                .keys()
+                .filter_map(|name| Some((sandboxer_path(name), script_source(&build.dom, name)?)))
+                .collect();
+            let changed: BTreeSet<_> = changed.iter().map(|name| sandboxer_path(name)).collect();
+            let mut selection = affected_tests(&modules, &read_test_modules(), &changed);
+            selection.append(&mut changed_tests);
+            if selection.is_empty() {
+                info!("No test modules are affected");
+                continue;
+            }
+            Some(selection)
+        };
+        recover(|| session.test(&build, selection.as_ref()));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn path(names: &[&str]) -> ModulePath {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn finds_required_paths() {
+        let source = "local A = require(\"./A\")\nlocal B = require '@self/B'\nlocal c = require(script.C)\n-- require(\"./D\")";
+        assert_eq!(required_paths(source), ["./A", "@self/B"]);
+    }
+
+    #[test]
+    fn resolves_require_paths() {
+        let list = sandboxer_path("Sandboxer.InstanceList");
+        assert_eq!(
+            resolve(&list, "./Config"),
+            Some(sandboxer_path("Sandboxer.Config"))
+        );
+        assert_eq!(
+            resolve(&sandboxer_path("Sandboxer"), "@self/Config"),
+            Some(sandboxer_path("Sandboxer.Config"))
+        );
+        assert_eq!(
+            resolve(&test_path("Sandboxer.test"), "../../Sandboxer/InstanceList"),
+            Some(sandboxer_path("Sandboxer.InstanceList"))
+        );
+        assert_eq!(
+            resolve(&test_path("Sandboxer.test"), "../TestFramework"),
+            Some(path(&["Sandboxer-Tests", "RunTests", "TestFramework"]))
+        );
+        assert_eq!(resolve(&list, "@pkg/Promise"), None);
+    }
+
+    #[test]
+    fn selects_tests_of_changed_modules() {
+        let project = crate::project::Project::read("../default.project.json");
+        let (_, sources) = project.build(Path::new(".."));
+        let sources: Vec<_> = sources
+            .iter()
+            .map(|(name, path)| (sandboxer_path(name), fs::read_to_string(path).unwrap()))
+            .collect();
+        let modules: Vec<_> = sources
+            .iter()
+            .map(|(path, source)| (path.clone(), source.as_str()))
+            .collect();
+        let mut tests: Vec<_> = fs::read_dir("src/luau/scripts")
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path
+                    .file_name()
+                    .unwrap()
+                    .to_string_lossy()
+                    .replace(".luau", "");
+                (name, fs::read_to_string(&path).unwrap())
+            })
+            .collect();
+        tests.sort_unstable();
+
+        let changed = BTreeSet::from([sandboxer_path("Sandboxer.InstanceSandboxer")]);
+        assert_eq!(
+            affected_tests(&modules, &tests, &changed),
+            BTreeSet::from(
+                [
+                    "InstanceSandboxer.test",
+                    "Integration.test",
+                    "Sandboxer.test"
+                ]
+                .map(str::to_owned)
+            )
+        );
+
+        // everything else requires InstanceList through the root module
+        let changed = BTreeSet::from([sandboxer_path("Sandboxer.InstanceList")]);
+        assert_eq!(affected_tests(&modules, &tests, &changed).len(), 4);
+    }
+
+    #[test]
+    fn classifies_changed_files() {
+        let scripts =
+            BTreeMap::from([(PathBuf::from("/repo/src/init.luau"), "Sandboxer".to_owned())]);
+        let (runner, tests) = (Path::new("/repo/luau"), Path::new("/repo/luau/scripts"));
+        let policy = Path::new("/repo/sandboxer.toml");
+        let classify = |path: &str| classify(Path::new(path), &scripts, runner, tests, policy);
+
+        assert!(
+            matches!(classify("/repo/luau/scripts/A.test.luau"), Some(Change::Test(name)) if name == "A.test")
+        );
+        assert!(matches!(
+            classify("/repo/luau/TestFramework.luau"),
+            Some(Change::Runner)
+        ));
+        assert!(matches!(
+            classify("/repo/src/New.luau"),
+            Some(Change::Rebuild)
+        ));
+        assert!(matches!(
+            classify("/repo/sandboxer.toml"),
+            Some(Change::Rebuild)
+        ));
+        assert!(classify("/repo/src/notes.md").is_none());
+        assert!(classify("/repo/other.toml").is_none());
+    }
+}
@@ -0,0 +1,489 @@
+//! A typed blocking client for the parts of the Roblox Open Cloud API that
+//! the Sandboxer tools use: Luau execution sessions and asset uploads.
+
+use std::{
+    fmt,
+    thread::sleep,
+    time::{Duration, Instant},
+};
+
+use reqwest::{
+    StatusCode,
+    blocking::{
+        RequestBuilder,
+        multipart::{Form, Part},
+    },
+};
+use serde::de::DeserializeOwned;
+
+pub mod types;
+use types::*;
+
+pub const DEFAULT_BASE_URL: &str = "https://apis.roblox.com";
+/// Longest wait between two polls of a task or operation.
+pub const MAX_POLL_DELAY: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+pub enum Error {
+    /// The request could not be sent or its response could not be read.
+    Http(reqwest::Error),
+    /// The API answered with an error status.
+    Status(StatusCode),
+    /// A long-running operation finished with an error.
+    Operation { code: i32, message: String },
+    /// A long-running operation finished without a result.
+    NoResponse(String),
+    /// A long-running operation was still running at the deadline.
+    Timeout(String),
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(e: reqwest::Error) -> Self {
+        // upload and download URLs are signed, so they stay out of errors
+        match e.status() {
+            Some(status) => Self::Status(status),
+            None => Self::Http(e.without_url()),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Http(e) => write!(f, "{e}"),
+            Self::Status(status) => write!(f, "HTTP {status}"),
+            Self::Operation { code, message } => write!(f, "{message} (code {code})"),
+            Self::NoResponse(path) => write!(f, "{path} finished without a result"),
+            Self::Timeout(path) => write!(f, "{path} did not finish before the deadline"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl Error {
+    /// Whether the request was refused because something it refers to, such
+    /// as a binary input, does not exist (anymore). Retrying it cannot help.
+    pub fn is_not_found(&self) -> bool {
+        matches!(self, Self::Status(StatusCode::NOT_FOUND | StatusCode::GONE))
+    }
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// An Open Cloud client authenticated with an API key. Cloning is cheap and
+/// shares the connection pool.
+#[derive(Debug, Clone)]
+pub struct Client {
+    http: reqwest::blocking::Client,
+    base_url: String,
+    api_key: String,
+}
+
+impl Client {
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            http: reqwest::blocking::Client::new(),
+            base_url: DEFAULT_BASE_URL.to_owned(),
+            api_key: api_key.into(),
+        }
+    }
+
+    /// Sends requests to `base_url` instead of [`DEFAULT_BASE_URL`], e.g. to
+    /// a mock server.
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into().trim_end_matches('/').to_owned();
+        self
+    }
+
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}/{path}", self.base_url)
+    }
+
+    fn send(&self, request: RequestBuilder) -> Result<reqwest::blocking::Response> {
+        Ok(request
+            .header("X-Api-Key", &self.api_key)
+            .send()?
+            .error_for_status()?)
+    }
+
+    fn json<T: DeserializeOwned>(&self, request: RequestBuilder) -> Result<T> {
+        Ok(self.send(request)?.json()?)
+    }
+
+    /// Creates a binary input that a task can load; upload its content with
+    /// [`Client::upload_binary_input`].
+    pub fn create_binary_input(
+        &self,
+        universe_id: u64,
+        size: usize,
+    ) -> Result<LuauExecutionBinaryInputResponse> {
+        self.json(
+            self.http
+                .post(self.url(&format!(
+                    "cloud/v2/universes/{universe_id}/luau-execution-session-task-binary-inputs"
+                )))
+                .json(&LuauExecutionBinaryInputRequest { size }),
+        )
+    }
+
+    pub fn upload_binary_input(
+        &self,
+        input: &LuauExecutionBinaryInputResponse,
+        content: Vec<u8>,
+    ) -> Result<()> {
+        // the upload URL is signed and must not get the API key
+        self.http
+            .put(&input.upload_url)
+            .body(content)
+            .send()?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Starts a task. Fails with [`Error::Status`] if the task is rejected,
+    /// e.g. because its binary input no longer exists.
+    pub fn create_task<O: DeserializeOwned>(
+        &self,
+        universe_id: u64,
+        place_id: u64,
+        request: &LuauExecutionTaskRequest,
+    ) -> Result<LuauExecutionTaskResponse<O>> {
+        self.json(
+            self.http
+                .post(self.url(&format!(
+                    "cloud/v2/universes/{universe_id}/places/{place_id}/luau-execution-session-tasks"
+                )))
+                .json(request),
+        )
+    }
+
+    /// Gets a task by the `path` returned when it was created.
+    pub fn get_task<O: DeserializeOwned>(
+        &self,
+        path: &str,
+    ) -> Result<LuauExecutionTaskResponse<O>> {
+        self.json(self.http.get(self.url(&format!("cloud/v2/{path}"))))
+    }
+
+    pub fn cancel_task(&self, path: &str) -> Result<()> {
+        self.send(
+            self.http
+                .post(self.url(&format!("cloud/v2/{path}:cancel")))
+                .header("Content-Type", "application/json")
+                .body("{}"),
+        )?;
+        Ok(())
+    }
+
+    /// Polls a task until it reaches a terminal state, doubling the delay
+    /// between polls up to [`MAX_POLL_DELAY`]. `on_wait` is called with the
+    /// state and delay before every wait. Past the `deadline`, the last
+    /// state is returned even if the task is still running.
+    pub fn poll_task<O: DeserializeOwned>(
+        &self,
+        path: &str,
+        deadline: Option<Instant>,
+        mut on_wait: impl FnMut(&LuauExecutionTaskState, Duration),
+    ) -> Result<LuauExecutionTaskResponse<O>> {
+        let mut delay = Duration::from_secs(1);
+        loop {
+            let task = self.get_task::<O>(path)?;
+            if task.state.is_terminal() {
+                return Ok(task);
+            }
+
+            let now = Instant::now();
+            let wait = match deadline {
+                Some(deadline) if now >= deadline => return Ok(task),
+                Some(deadline) => delay.min(deadline - now),
+                None => delay,
+            };
+            on_wait(&task.state, wait);
+            sleep(wait);
+            delay = MAX_POLL_DELAY.min(delay * 2);
+        }
+    }
+
+    /// The structured log messages of a task, a page at a time.
+    pub fn task_logs(&self, path: &str) -> TaskLogs<'_> {
+        TaskLogs {
+            client: self,
+            path: path.to_owned(),
+            page_token: Some(String::new()),
+        }
+    }
+
+    /// Downloads from a URL returned by the API, like a binary output.
+    pub fn download(&self, url: &str) -> Result<Vec<u8>> {
+        Ok(self
+            .http
+            .get(url)
+            .send()?
+            .error_for_status()?
+            .bytes()?
+            .to_vec())
+    }
+
+    /// Uploads `content` as the new version of the model `asset_id`. The
+    /// upload is a long-running operation; see [`Client::wait_for_operation`].
+    pub fn update_asset(
+        &self,
+        asset_id: u64,
+        file_name: &str,
+        content: Vec<u8>,
+    ) -> Result<AssetOperation> {
+        let request = serde_json::to_string(&AssetUpdateRequest {
+            asset_id: asset_id.to_string(),
+        })
+        .expect("Asset update requests always serialize");
+        let content = Part::bytes(content)
+            .file_name(file_name.to_owned())
+            .mime_str("model/x-rbxm")?;
+
+        self.json(
+            self.http
+                .patch(self.url(&format!("assets/v1/assets/{asset_id}")))
+                .multipart(
+                    Form::new()
+                        .text("request", request)
+                        .part("fileContent", content),
+                ),
+        )
+    }
+
+    pub fn get_operation(&self, path: &str) -> Result<AssetOperation> {
+        self.json(self.http.get(self.url(&format!("assets/v1/{path}"))))
+    }
+
+    /// Polls an operation until it is done, with the same backoff as
+    /// [`Client::poll_task`], and returns the asset it produced. `on_wait`
+    /// is called with the delay before every wait. Past the `deadline`, a
+    /// [`Error::Timeout`] is returned.
+    pub fn wait_for_operation(
+        &self,
+        mut operation: AssetOperation,
+        deadline: Option<Instant>,
+        mut on_wait: impl FnMut(Duration),
+    ) -> Result<Asset> {
+        let mut delay = Duration::from_secs(1);
+        while !operation.done {
+            operation = self.get_operation(&operation.path)?;
+            if operation.done {
+                break;
+            }
+
+            let now = Instant::now();
+            let wait = match deadline {
+                Some(deadline) if now >= deadline => return Err(Error::Timeout(operation.path)),
+                Some(deadline) => delay.min(deadline - now),
+                None => delay,
+            };
+            on_wait(wait);
+            sleep(wait);
+            delay = MAX_POLL_DELAY.min(delay * 2);
+        }
+
+        match (operation.response, operation.error) {
+            (_, Some(error)) => Err(Error::Operation {
+                code: error.code,
+                message: error.message,
+            }),
+            (Some(asset), None) => Ok(asset),
+            (None, None) => Err(Error::NoResponse(operation.path)),
+        }
+    }
+}
+
+/// Iterator over the pages of the logs of a task; see [`Client::task_logs`].
+/// Stops after the first error.
+pub struct TaskLogs<'a> {
+    client: &'a Client,
+    path: String,
+    /// `None` once the last page has been fetched.
+    page_token: Option<String>,
+}
+
+impl Iterator for TaskLogs<'_> {
+    type Item = Result<Vec<LuauExecutionTaskLogEntry>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let page_token = self.page_token.take()?;
+        let url = self.client.url(&format!(
+            "cloud/v2/{}/logs?view=STRUCTURED&nextPageToken={page_token}",
+            self.path
+        ));
+        let page = self
+            .client
+            .json::<LuauExecutionTaskLogsResponse>(self.client.http.get(url));
+        Some(page.map(|page| {
+            self.page_token = page.next_page_token.filter(|token| !token.is_empty());
+            page.luau_execution_session_task_logs
+                .into_iter()
+                .flat_map(|log| log.structured_messages)
+                .collect()
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread::{self, JoinHandle},
+    };
+
+    use super::*;
+
+    /// Answers one request per response on a local port, returning the base
+    /// URL and the request lines it received.
+    fn mock_server(responses: Vec<&'static str>) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let handle = thread::spawn(move || {
+            responses
+                .into_iter()
+                .map(|body| {
+                    let (stream, _) = listener.accept().unwrap();
+                    let mut reader = BufReader::new(stream);
+                    let mut request = String::new();
+                    reader.read_line(&mut request).unwrap();
+                    let mut length = 0;
+                    loop {
+                        let mut header = String::new();
+                        reader.read_line(&mut header).unwrap();
+                        if header.trim().is_empty() {
+                            break;
+                        }
+                        if let Some((name, value)) = header.split_once(':')
+                            && name.eq_ignore_ascii_case("content-length")
+                        {
+                            length = value.trim().parse().unwrap();
+                        }
+                    }
+                    reader.read_exact(&mut vec![0; length]).unwrap();
+                    write!(
+                        reader.get_mut(),
+                        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\n\
+                         Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                        body.len()
+                    )
+                    .unwrap();
+                    request.trim().to_owned()
+                })
+                .collect()
+        });
+        (url, handle)
+    }
+
+    const TASK: &str = r#"{"path": "universes/1/places/2/luau-execution-session-tasks/t",
+        "user": "1", "state": "COMPLETE", "binaryInput": "", "enableBinaryOutput": false,
+        "output": {"results": [{"success": true}]}}"#;
+
+    #[test]
+    fn polls_tasks_and_pages_logs() {
+        let (url, server) = mock_server(vec![
+            TASK,
+            r#"{"luauExecutionSessionTaskLogs": [{"path": "l", "structuredMessages": [
+                {"message": "a", "createTime": "2026-01-01T00:00:00Z", "messageType": "OUTPUT"}
+            ]}], "nextPageToken": "next"}"#,
+            r#"{"luauExecutionSessionTaskLogs": [{"path": "l", "structuredMessages": [
+                {"message": "b", "createTime": "2026-01-01T00:00:01Z", "messageType": "ERROR"}
+            ]}], "nextPageToken": ""}"#,
+        ]);
+        let client = Client::new("key").with_base_url(format!("{url}/"));
+        let path = "universes/1/places/2/luau-execution-session-tasks/t";
+
+        let task = client
+            .poll_task::<LuauExecutionTaskOutput>(path, None, |_, _| {})
+            .unwrap();
+        assert_eq!(task.state, LuauExecutionTaskState::Complete);
+        assert_eq!(
+            task.output.unwrap().results,
+            [serde_json::json!({"success": true})]
+        );
+
+        let messages: Vec<_> = client
+            .task_logs(path)
+            .flat_map(|page| page.unwrap().into_iter().map(|entry| entry.message))
+            .collect();
+        assert_eq!(messages, ["a", "b"]);
+
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                format!("GET /cloud/v2/{path} HTTP/1.1"),
+                format!("GET /cloud/v2/{path}/logs?view=STRUCTURED&nextPageToken= HTTP/1.1"),
+                format!("GET /cloud/v2/{path}/logs?view=STRUCTURED&nextPageToken=next HTTP/1.1"),
+            ]
+        );
+    }
+
+    #[test]
+    fn uploads_assets() {
+        let (url, server) = mock_server(vec![
+            r#"{"path": "operations/abc", "done": false}"#,
+            r#"{"path": "operations/abc", "done": true, "response": {
+                "assetId": "1234", "revisionId": "7", "revisionCreateTime": "2026-01-01T00:00:00Z"
+            }}"#,
+        ]);
+        let client = Client::new("key").with_base_url(url);
+        let operation = client
+            .update_asset(1234, "Sandboxer.rbxm", b"<roblox!".to_vec())
+            .unwrap();
+        assert_eq!(
+            client.wait_for_operation(operation, None, |_| {}).unwrap(),
+            Asset {
+                asset_id: "1234".to_owned(),
+                revision_id: "7".to_owned(),
+                revision_create_time: Some("2026-01-01T00:00:00Z".to_owned()),
+            }
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            [
+                "PATCH /assets/v1/assets/1234 HTTP/1.1",
+                "GET /assets/v1/operations/abc HTTP/1.1"
+            ]
+        );
+    }
+
+    #[test]
+    fn reports_failed_operations() {
+        let client = Client::new("key");
+        let operation = serde_json::from_str(
+            r#"{"path": "operations/abc", "done": true, "error": {"code": 9, "message": "Asset is moderated"}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            client
+                .wait_for_operation(operation, None, |_| {})
+                .unwrap_err()
+                .to_string(),
+            "Asset is moderated (code 9)"
+        );
+    }
+
+    #[test]
+    fn stops_waiting_at_the_deadline() {
+        let (url, server) = mock_server(vec![r#"{"path": "operations/abc", "done": false}"#]);
+        let client = Client::new("key").with_base_url(url);
+        let operation = serde_json::from_str(r#"{"path": "operations/abc"}"#).unwrap();
+        assert_eq!(
+            client
+                .wait_for_operation(operation, Some(Instant::now()), |_| {})
+                .unwrap_err()
+                .to_string(),
+            "operations/abc did not finish before the deadline"
+        );
+        assert_eq!(
+            server.join().unwrap(),
+            ["GET /assets/v1/operations/abc HTTP/1.1"]
+        );
+    }
+}
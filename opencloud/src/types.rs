@@ -0,0 +1,152 @@
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Debug)]
+pub struct LuauExecutionBinaryInputRequest {
+    pub size: usize,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionBinaryInputResponse {
+    pub path: String,
+    pub size: usize,
+    #[serde(rename = "uploadUri")]
+    pub upload_url: String,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionTaskRequest<'a> {
+    pub script: &'a str,
+    pub timeout: &'a str,
+    pub binary_input: String,
+    pub enable_binary_output: bool,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum LuauExecutionError {
+    ScriptError,
+    DeadlineExceeded,
+    OutputSizeLimitExceeded,
+    InternalError,
+    #[serde(rename = "ERROR_CODE_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct LuauExecutionTaskError {
+    pub code: LuauExecutionError,
+    pub message: String,
+}
+
+/// What the script of a task returned, when the caller has no type for it.
+#[derive(Deserialize, Debug)]
+pub struct LuauExecutionTaskOutput {
+    pub results: Vec<serde_json::Value>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LogMessageType {
+    Error,
+    Warning,
+    Info,
+    Output,
+    #[serde(rename = "MESSAGE_TYPE_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionTaskLogEntry {
+    pub message: String,
+    pub create_time: String,
+    pub message_type: LogMessageType,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionTaskLog {
+    pub path: String,
+    pub messages: Option<[(); 0]>,
+    pub structured_messages: Vec<LuauExecutionTaskLogEntry>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionTaskLogsResponse {
+    pub luau_execution_session_task_logs: Vec<LuauExecutionTaskLog>,
+    pub next_page_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum LuauExecutionTaskState {
+    Queued,
+    Processing,
+    Cancelled,
+    Complete,
+    Failed,
+    #[serde(rename = "STATE_UNSPECIFIED", other)]
+    Unspecified,
+}
+
+impl LuauExecutionTaskState {
+    /// Whether the task has stopped and will not change state again.
+    pub fn is_terminal(&self) -> bool {
+        matches!(self, Self::Complete | Self::Failed | Self::Cancelled)
+    }
+}
+
+/// A Luau execution session task. `O` is the type of its output, e.g. a
+/// struct with the `results` the script returns.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct LuauExecutionTaskResponse<O = LuauExecutionTaskOutput> {
+    pub path: String,
+    pub create_time: Option<String>,
+    pub update_time: Option<String>,
+    pub user: String,
+    pub state: LuauExecutionTaskState,
+    pub script: Option<String>,
+    pub timeout: Option<String>,
+    pub error: Option<LuauExecutionTaskError>,
+    pub output: Option<O>,
+    pub binary_input: String,
+    pub enable_binary_output: bool,
+    #[serde(rename = "binaryOutputUri")]
+    pub binary_output_url: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetUpdateRequest {
+    pub asset_id: String,
+}
+
+#[derive(Deserialize, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct Asset {
+    pub asset_id: String,
+    pub revision_id: String,
+    pub revision_create_time: Option<String>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct AssetOperationError {
+    #[serde(default)]
+    pub code: i32,
+    pub message: String,
+}
+
+/// A long-running operation of the assets API.
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct AssetOperation {
+    pub path: String,
+    #[serde(default)]
+    pub done: bool,
+    pub error: Option<AssetOperationError>,
+    pub response: Option<Asset>,
+}